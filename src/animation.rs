@@ -1,9 +1,17 @@
-use image::{imageops, io::Reader};
+use image::imageops;
 use luminance::context::GraphicsContext;
 use luminance_front::Backend;
 
-use crate::object::{ResourceManager, TextureResource};
-use std::time::{Duration, Instant};
+use crate::{
+    assets::{self, AnimationAssets, FrameAsset},
+    error::Result,
+    object::{ResourceManager, TextureResource},
+};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 pub struct Frame {
     texture: TextureResource,
@@ -14,6 +22,7 @@ pub struct Animation {
     frames: Vec<Frame>,
     start_time: Instant,
     looping: bool,
+    paused_at: Option<Instant>,
 }
 
 impl Animation {
@@ -22,6 +31,7 @@ impl Animation {
             frames,
             start_time: Instant::now(),
             looping: false,
+            paused_at: None,
         }
     }
 
@@ -34,8 +44,20 @@ impl Animation {
         self.looping = true;
     }
 
+    pub fn pause(&mut self) {
+        self.paused_at.get_or_insert_with(Instant::now);
+    }
+
+    // Continues from the frame shown when paused
+    pub fn resume(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            self.start_time += paused_at.elapsed();
+        }
+    }
+
     pub fn get_current_texture(&self) -> TextureResource {
-        let mut elapsed = self.start_time.elapsed();
+        let now = self.paused_at.unwrap_or_else(Instant::now);
+        let mut elapsed = now - self.start_time;
 
         if self.looping {
             let total_duration: Duration = self.frames.iter().map(|f| f.duration).sum();
@@ -61,41 +83,54 @@ pub struct GameAnimations {
 }
 
 impl GameAnimations {
+    // Frames sharing an image share a texture
+    fn load_animation(
+        rm: &mut ResourceManager,
+        ctxt: &mut impl GraphicsContext<Backend = Backend>,
+        textures: &mut HashMap<PathBuf, TextureResource>,
+        frames: &[FrameAsset],
+    ) -> Result<Animation> {
+        let frames = frames
+            .iter()
+            .map(|f| {
+                let texture = match textures.get(&f.texture) {
+                    Some(&texture) => texture,
+                    None => {
+                        let img = assets::load_image_or_placeholder(&f.texture);
+                        let texture = rm.make_texture(ctxt, &imageops::flip_vertical(&img))?;
+                        textures.insert(f.texture.clone(), texture);
+                        texture
+                    }
+                };
+                Ok(Frame {
+                    texture,
+                    duration: f.duration(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Animation::new(frames))
+    }
+
     pub fn new(
         rm: &mut ResourceManager,
         ctxt: &mut impl GraphicsContext<Backend = Backend>,
-    ) -> Self {
-        let frame1 = Reader::open("textures/lumberjack-chop-1.png")
-            .unwrap()
-            .decode()
-            .unwrap()
-            .into_rgba8();
-        let frame2 = Reader::open("textures/lumberjack-chop-2.png")
-            .unwrap()
-            .decode()
-            .unwrap()
-            .into_rgba8();
-        let frame1 = rm.make_texture(ctxt, &imageops::flip_vertical(&frame1));
-        let frame2 = rm.make_texture(ctxt, &imageops::flip_vertical(&frame2));
-        Self {
-            chop: Animation::new(vec![
-                Frame {
-                    texture: frame1,
-                    duration: Duration::from_millis(50),
-                },
-                Frame {
-                    texture: frame2,
-                    duration: Duration::from_millis(200),
-                },
-                Frame {
-                    texture: frame1,
-                    duration: Duration::from_millis(100),
-                },
-            ]),
-        }
+        assets: &AnimationAssets,
+    ) -> Result<Self> {
+        let mut textures = HashMap::new();
+        Ok(Self {
+            chop: Self::load_animation(rm, ctxt, &mut textures, &assets.chop)?,
+        })
     }
 
     pub fn update(&mut self) {
         self.chop.start();
     }
+
+    pub fn pause(&mut self) {
+        self.chop.pause();
+    }
+
+    pub fn resume(&mut self) {
+        self.chop.resume();
+    }
 }
@@ -0,0 +1,151 @@
+use image::{io::Reader, Rgba, RgbaImage};
+use serde::Deserialize;
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    error::{Error, Result},
+    fonts::FontRole,
+};
+
+pub const MANIFEST_PATH: &str = "data/assets.json";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FrameAsset {
+    pub texture: PathBuf,
+    pub duration_ms: u64,
+}
+
+impl FrameAsset {
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.duration_ms)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MusicAsset {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub fade_in_ms: u64,
+}
+
+impl MusicAsset {
+    pub fn fade_in(&self) -> Duration {
+        Duration::from_millis(self.fade_in_ms)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextureAssets {
+    pub log: PathBuf,
+    pub background: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnimationAssets {
+    pub chop: Vec<FrameAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SoundAssets {
+    pub chop: PathBuf,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MusicAssets {
+    pub bgm: MusicAsset,
+}
+
+// Fonts are named by their file in `dir` without the extension. Each kind of
+// text lists fonts to try in order for characters the previous ones lack.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FontAssets {
+    pub dir: PathBuf,
+    pub body: Vec<String>,
+    pub title: Vec<String>,
+    pub score: Vec<String>,
+}
+
+impl Default for FontAssets {
+    fn default() -> Self {
+        let names = vec!["Courier New".to_string()];
+        Self {
+            dir: PathBuf::from("fonts"),
+            body: names.clone(),
+            title: names.clone(),
+            score: names,
+        }
+    }
+}
+
+impl FontAssets {
+    pub fn names(&self, role: FontRole) -> &[String] {
+        match role {
+            FontRole::Body => &self.body,
+            FontRole::Title => &self.title,
+            FontRole::Score => &self.score,
+        }
+    }
+}
+
+// Every file the game loads, so that it can be reskinned without code changes
+#[derive(Debug, Clone, Deserialize)]
+pub struct AssetManifest {
+    pub textures: TextureAssets,
+    pub animations: AnimationAssets,
+    pub sounds: SoundAssets,
+    pub music: MusicAssets,
+    #[serde(default)]
+    pub fonts: FontAssets,
+}
+
+impl AssetManifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let manifest: Self = load_json(path)?;
+        if manifest.animations.chop.is_empty() {
+            let reason = "animation chop has no frames".to_string();
+            return Err(Error::Invalid(path.to_path_buf(), reason));
+        }
+        Ok(manifest)
+    }
+}
+
+pub fn open_file(path: &Path) -> Result<File> {
+    File::open(path).map_err(|e| Error::Io(path.to_path_buf(), e))
+}
+
+pub fn load_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T> {
+    let file = open_file(path)?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| Error::Json(path.to_path_buf(), e))
+}
+
+pub fn load_image(path: &Path) -> Result<RgbaImage> {
+    let reader = Reader::open(path).map_err(|e| Error::Io(path.to_path_buf(), e))?;
+    let img = reader
+        .decode()
+        .map_err(|e| Error::Image(path.to_path_buf(), e))?;
+    Ok(img.into_rgba8())
+}
+
+// Magenta and black checkerboard, hard to miss in game
+pub fn placeholder_image() -> RgbaImage {
+    RgbaImage::from_fn(8, 8, |x, y| {
+        if (x + y) % 2 == 0 {
+            Rgba([255, 0, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+pub fn load_image_or_placeholder(path: &Path) -> RgbaImage {
+    load_image(path).unwrap_or_else(|e| {
+        eprintln!("{}\nusing a placeholder texture", e);
+        placeholder_image()
+    })
+}
@@ -1,60 +1,298 @@
-use std::{fs::File, io::BufReader, time::Duration};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::BufReader, path::Path};
 
+use crate::{
+    assets::{self, AssetManifest},
+    error::{Error, Result},
+};
 use rodio::{
     source::{Buffered, FadeIn, Repeat},
     Decoder, OutputStream, OutputStreamHandle, Sample, Sink, Source,
 };
 
+// Sounds that are `None` are silent
 pub struct AudioResources {
-    pub chop: Buffered<Decoder<BufReader<File>>>,
-    pub bgm: FadeIn<Repeat<Decoder<BufReader<File>>>>,
+    pub chop: Option<Buffered<Decoder<BufReader<File>>>>,
+    pub bgm: Option<FadeIn<Repeat<Decoder<BufReader<File>>>>>,
+}
+
+fn load_sound(path: &Path) -> Result<Decoder<BufReader<File>>> {
+    let file = assets::open_file(path)?;
+    Decoder::new(BufReader::new(file)).map_err(|e| Error::Audio(path.to_path_buf(), e))
+}
+
+// A sound that fails to load is printed and left silent
+fn load_or_silent(path: &Path) -> Option<Decoder<BufReader<File>>> {
+    load_sound(path)
+        .map_err(|e| eprintln!("{}\ncontinuing without this sound", e))
+        .ok()
 }
 
 impl AudioResources {
-    pub fn new() -> Self {
-        let chop_file = std::fs::File::open("audios/chop.wav").unwrap();
-        let chop = Decoder::new(BufReader::new(chop_file)).unwrap().buffered();
-        let bgm_file = std::fs::File::open("audios/bird-loop.mp3").unwrap();
-        let bgm = Decoder::new(BufReader::new(bgm_file))
-            .unwrap()
-            .repeat_infinite()
-            .fade_in(Duration::from_secs(2));
-        Self { chop, bgm }
+    pub fn new(manifest: &AssetManifest) -> Self {
+        let bgm = &manifest.music.bgm;
+        Self {
+            chop: load_or_silent(&manifest.sounds.chop).map(Source::buffered),
+            bgm: load_or_silent(&bgm.path)
+                .map(|music| music.repeat_infinite().fade_in(bgm.fade_in())),
+        }
+    }
+
+    pub fn silent() -> Self {
+        Self {
+            chop: None,
+            bgm: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Channel {
+    Master,
+    Music,
+    Effects,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ChannelVolume {
+    pub volume: f32,
+    pub muted: bool,
+}
+
+impl Default for ChannelVolume {
+    fn default() -> Self {
+        Self {
+            volume: 1.,
+            muted: false,
+        }
+    }
+}
+
+impl ChannelVolume {
+    fn gain(&self) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.volume
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VolumeSettings {
+    pub master: ChannelVolume,
+    pub music: ChannelVolume,
+    pub effects: ChannelVolume,
+}
+
+impl VolumeSettings {
+    pub fn channel(&self, channel: Channel) -> &ChannelVolume {
+        match channel {
+            Channel::Master => &self.master,
+            Channel::Music => &self.music,
+            Channel::Effects => &self.effects,
+        }
+    }
+
+    pub fn channel_mut(&mut self, channel: Channel) -> &mut ChannelVolume {
+        match channel {
+            Channel::Master => &mut self.master,
+            Channel::Music => &mut self.music,
+            Channel::Effects => &mut self.effects,
+        }
+    }
+
+    // Brings volumes edited by hand back into [0, 1]
+    pub fn clamp(&mut self) {
+        for volume in [&mut self.master, &mut self.music, &mut self.effects].iter_mut() {
+            volume.volume = volume.volume.clamp(0., 1.);
+        }
+    }
+
+    // Volume of a sound on the channel, taking the master channel into account
+    pub fn effective(&self, channel: Channel) -> f32 {
+        self.master.gain() * self.channel(channel).gain()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sound {
+    Chop,
+    Music,
+}
+
+impl Sound {
+    pub fn channel(self) -> Channel {
+        match self {
+            Sound::Chop => Channel::Effects,
+            Sound::Music => Channel::Music,
+        }
     }
 }
 
+enum Output {
+    Device {
+        sinks: Vec<(Channel, Sink)>,
+        _stream: OutputStream,
+        stream_handle: OutputStreamHandle,
+    },
+    // Used when there is no sound card, optionally keeping track of what was played
+    Null {
+        record: bool,
+    },
+}
+
 pub struct AudioPlayer {
-    volume: f32,
-    sinks: Vec<Sink>,
-    _stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    volumes: VolumeSettings,
+    output: Output,
+    played: Vec<Sound>,
 }
 
 impl AudioPlayer {
-    pub fn new() -> Self {
-        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
+    // Falls back to a silent player if no audio device is available
+    pub fn new(volumes: VolumeSettings) -> Self {
+        Self::try_new(volumes.clone()).unwrap_or_else(|e| {
+            eprintln!("{}\ncontinuing without sound", e);
+            Self::null(volumes, false)
+        })
+    }
+
+    pub fn try_new(volumes: VolumeSettings) -> Result<Self> {
+        let (_stream, stream_handle) = OutputStream::try_default()?;
+        Ok(Self {
+            volumes,
+            output: Output::Device {
+                sinks: vec![],
+                _stream,
+                stream_handle,
+            },
+            played: vec![],
+        })
+    }
+
+    pub fn null(volumes: VolumeSettings, record: bool) -> Self {
         Self {
-            volume: 1.,
-            sinks: vec![],
-            _stream,
-            stream_handle,
+            volumes,
+            output: Output::Null { record },
+            played: vec![],
+        }
+    }
+
+    // Sounds requested so far, only kept by a recording null player
+    pub fn played(&self) -> &[Sound] {
+        &self.played
+    }
+
+    pub fn volumes(&self) -> &VolumeSettings {
+        &self.volumes
+    }
+
+    pub fn set_volumes(&mut self, volumes: VolumeSettings) {
+        self.volumes = volumes;
+        self.apply_volumes();
+    }
+
+    pub fn set_volume(&mut self, channel: Channel, volume: f32) {
+        self.volumes.channel_mut(channel).volume = volume.clamp(0., 1.);
+        self.apply_volumes();
+    }
+
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        self.volumes.channel_mut(channel).muted = muted;
+        self.apply_volumes();
+    }
+
+    // Updates sounds that are already playing
+    fn apply_volumes(&mut self) {
+        if let Output::Device { sinks, .. } = &mut self.output {
+            for (channel, sink) in sinks.iter() {
+                sink.set_volume(self.volumes.effective(*channel));
+            }
         }
     }
 
-    fn remove_used_sinks(&mut self) {
-        self.sinks.retain(|s| !Sink::empty(s));
+    pub fn play(&mut self, sound: Sound, resources: &AudioResources) -> Result<()> {
+        // Recorded even when the sound did not load, tests run without assets
+        if let Output::Null { record: true } = self.output {
+            self.played.push(sound);
+        }
+        match sound {
+            Sound::Chop => match &resources.chop {
+                Some(source) => self.play_source(sound, source.clone()),
+                None => Ok(()),
+            },
+            Sound::Music => match &resources.bgm {
+                Some(source) => self.play_source(sound, source.clone()),
+                None => Ok(()),
+            },
+        }
     }
 
-    pub fn play<S>(&mut self, source: S)
+    fn play_source<S>(&mut self, sound: Sound, source: S) -> Result<()>
     where
         S: Source + Send + 'static,
         S::Item: Sample,
         S::Item: Send,
     {
-        let sink = Sink::try_new(&self.stream_handle).unwrap();
-        sink.set_volume(self.volume);
-        sink.append(source);
-        self.sinks.push(sink);
-        self.remove_used_sinks();
+        if let Output::Device {
+            sinks,
+            stream_handle,
+            ..
+        } = &mut self.output
+        {
+            let channel = sound.channel();
+            let sink = Sink::try_new(stream_handle)?;
+            sink.set_volume(self.volumes.effective(channel));
+            sink.append(source);
+            sinks.push((channel, sink));
+            sinks.retain(|(_, s)| !Sink::empty(s));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_sounds_without_resources() {
+        let resources = AudioResources::silent();
+        let mut player = AudioPlayer::null(VolumeSettings::default(), true);
+        player.play(Sound::Chop, &resources).unwrap();
+        player.play(Sound::Music, &resources).unwrap();
+        player.play(Sound::Chop, &resources).unwrap();
+        assert_eq!(player.played(), &[Sound::Chop, Sound::Music, Sound::Chop]);
+    }
+
+    #[test]
+    fn only_records_when_asked() {
+        let mut player = AudioPlayer::null(VolumeSettings::default(), false);
+        player.play(Sound::Chop, &AudioResources::silent()).unwrap();
+        assert!(player.played().is_empty());
+    }
+
+    #[test]
+    fn master_scales_channels() {
+        let mut volumes = VolumeSettings::default();
+        volumes.master.volume = 0.5;
+        volumes.effects.volume = 0.5;
+        assert_eq!(volumes.effective(Channel::Effects), 0.25);
+        assert_eq!(volumes.effective(Channel::Music), 0.5);
+        volumes.master.muted = true;
+        assert_eq!(volumes.effective(Channel::Music), 0.);
+    }
+
+    #[test]
+    fn clamp_limits_volumes() {
+        let mut volumes = VolumeSettings::default();
+        volumes.master.volume = 3.;
+        volumes.music.volume = -1.;
+        volumes.effects.volume = 0.4;
+        volumes.clamp();
+        assert_eq!(volumes.master.volume, 1.);
+        assert_eq!(volumes.music.volume, 0.);
+        assert_eq!(volumes.effects.volume, 0.4);
     }
 }
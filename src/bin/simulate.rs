@@ -0,0 +1,130 @@
+use lumber::{
+    difficulty::Difficulties,
+    game::GameMode,
+    simulation::{
+        GameResult, HumanPolicy, PerfectPolicy, Policy, RandomPolicy, Report, Simulation,
+    },
+};
+use std::{path::PathBuf, process::exit, str::FromStr, time::Duration};
+
+const USAGE: &str = "usage: simulate [--games N] [--seed N] [--policy perfect|random|human] \
+[--reaction SECS] [--error-rate P] [--max-chops N] [--bucket N] [--physics] \
+[--mode classic|casual|expert] [--difficulty FILE]";
+
+struct Args {
+    games: u64,
+    seed: u64,
+    policy: String,
+    reaction: f64,
+    error_rate: f64,
+    max_chops: u32,
+    bucket: u32,
+    physics: bool,
+    mode: GameMode,
+    difficulty: Option<PathBuf>,
+}
+
+fn parse_value<T: FromStr>(flag: &str, value: Option<String>) -> T {
+    match value.as_deref().map(str::parse) {
+        Some(Ok(v)) => v,
+        _ => {
+            eprintln!("invalid value for {}\n{}", flag, USAGE);
+            exit(1);
+        }
+    }
+}
+
+fn parse_args() -> Args {
+    let mut args = Args {
+        games: 1000,
+        seed: 0,
+        policy: "human".to_string(),
+        reaction: 0.25,
+        error_rate: 0.02,
+        max_chops: 10_000,
+        bucket: 10,
+        physics: false,
+        mode: GameMode::Classic,
+        difficulty: None,
+    };
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--games" => args.games = parse_value(&arg, iter.next()),
+            "--seed" => args.seed = parse_value(&arg, iter.next()),
+            "--policy" => args.policy = parse_value(&arg, iter.next()),
+            "--reaction" => args.reaction = parse_value(&arg, iter.next()),
+            "--error-rate" => args.error_rate = parse_value(&arg, iter.next()),
+            "--max-chops" => args.max_chops = parse_value(&arg, iter.next()),
+            "--bucket" => args.bucket = parse_value::<u32>(&arg, iter.next()).max(1),
+            "--physics" => args.physics = true,
+            "--mode" => args.mode = parse_value(&arg, iter.next()),
+            "--difficulty" => args.difficulty = Some(parse_value(&arg, iter.next())),
+            _ => {
+                eprintln!("unknown argument: {}\n{}", arg, USAGE);
+                exit(1);
+            }
+        }
+    }
+    // Both would otherwise panic when the policy samples them
+    if !(args.reaction.is_finite() && args.reaction >= 0.) {
+        eprintln!("invalid value for --reaction\n{}", USAGE);
+        exit(1);
+    }
+    // The human policy spreads its reaction times around the mean
+    if args.policy == "human" && args.reaction == 0. {
+        eprintln!(
+            "--reaction must be positive with the human policy\n{}",
+            USAGE
+        );
+        exit(1);
+    }
+    if !(0. ..=1.).contains(&args.error_rate) {
+        eprintln!("invalid value for --error-rate\n{}", USAGE);
+        exit(1);
+    }
+    args
+}
+
+fn run_all(args: &Args, sim: &Simulation, policy: &mut impl Policy) -> Vec<GameResult> {
+    (0..args.games)
+        .map(|i| sim.run_game(policy, args.seed.wrapping_add(i)))
+        .collect()
+}
+
+fn main() {
+    let args = parse_args();
+    let difficulties = match &args.difficulty {
+        Some(path) => Difficulties::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            exit(1);
+        }),
+        None => Difficulties::load_or_default(),
+    };
+    let sim = Simulation {
+        profile: difficulties.profile(args.mode),
+        max_chops: args.max_chops,
+        physics: args.physics,
+    };
+    let reaction = Duration::from_secs_f64(args.reaction);
+    let results = match args.policy.as_str() {
+        "perfect" => run_all(&args, &sim, &mut PerfectPolicy { reaction }),
+        "random" => run_all(&args, &sim, &mut RandomPolicy::new(reaction, args.seed)),
+        "human" => run_all(
+            &args,
+            &sim,
+            &mut HumanPolicy::new(
+                args.reaction,
+                args.reaction / 4.,
+                args.error_rate,
+                args.seed,
+            ),
+        ),
+        p => {
+            eprintln!("unknown policy: {}\n{}", p, USAGE);
+            exit(1);
+        }
+    };
+    let report = Report::new(&results, args.bucket);
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+}
@@ -1,16 +1,127 @@
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 
-use glfw::{Action, Key, WindowEvent};
+use glfw::{Action, GamepadButton, Key, WindowEvent};
 
-use crate::{game::PlayerAction, menu::MenuAction};
+use crate::{game::PlayerAction, gamepad::PadInput, menu::MenuAction};
 
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GameAction {
     Left,
     Right,
     Down,
     Up,
     Enter,
+    Back,
+}
+
+// Keys that can be bound, with the names used in the settings file
+const KEY_NAMES: &[(Key, &str)] = &[
+    (Key::A, "A"),
+    (Key::B, "B"),
+    (Key::C, "C"),
+    (Key::D, "D"),
+    (Key::E, "E"),
+    (Key::F, "F"),
+    (Key::G, "G"),
+    (Key::H, "H"),
+    (Key::I, "I"),
+    (Key::J, "J"),
+    (Key::K, "K"),
+    (Key::L, "L"),
+    (Key::M, "M"),
+    (Key::N, "N"),
+    (Key::O, "O"),
+    (Key::P, "P"),
+    (Key::Q, "Q"),
+    (Key::R, "R"),
+    (Key::S, "S"),
+    (Key::T, "T"),
+    (Key::U, "U"),
+    (Key::V, "V"),
+    (Key::W, "W"),
+    (Key::X, "X"),
+    (Key::Y, "Y"),
+    (Key::Z, "Z"),
+    (Key::Num0, "0"),
+    (Key::Num1, "1"),
+    (Key::Num2, "2"),
+    (Key::Num3, "3"),
+    (Key::Num4, "4"),
+    (Key::Num5, "5"),
+    (Key::Num6, "6"),
+    (Key::Num7, "7"),
+    (Key::Num8, "8"),
+    (Key::Num9, "9"),
+    (Key::Up, "Up"),
+    (Key::Down, "Down"),
+    (Key::Left, "Left"),
+    (Key::Right, "Right"),
+    (Key::Enter, "Enter"),
+    (Key::Space, "Space"),
+    (Key::Escape, "Escape"),
+    (Key::Backspace, "Backspace"),
+    (Key::Tab, "Tab"),
+    (Key::LeftShift, "LeftShift"),
+    (Key::RightShift, "RightShift"),
+    (Key::LeftControl, "LeftControl"),
+    (Key::RightControl, "RightControl"),
+    (Key::Comma, "Comma"),
+    (Key::Period, "Period"),
+    (Key::Semicolon, "Semicolon"),
+    (Key::Slash, "Slash"),
+    (Key::Kp0, "Keypad0"),
+    (Key::Kp1, "Keypad1"),
+    (Key::Kp2, "Keypad2"),
+    (Key::Kp3, "Keypad3"),
+    (Key::Kp4, "Keypad4"),
+    (Key::Kp5, "Keypad5"),
+    (Key::Kp6, "Keypad6"),
+    (Key::Kp7, "Keypad7"),
+    (Key::Kp8, "Keypad8"),
+    (Key::Kp9, "Keypad9"),
+    (Key::KpEnter, "KeypadEnter"),
+];
+
+// Gamepad buttons share the settings file with keys, hence the prefix
+const BUTTON_NAMES: &[(GamepadButton, &str)] = &[
+    (GamepadButton::ButtonA, "PadA"),
+    (GamepadButton::ButtonB, "PadB"),
+    (GamepadButton::ButtonX, "PadX"),
+    (GamepadButton::ButtonY, "PadY"),
+    (GamepadButton::ButtonLeftBumper, "PadLeftBumper"),
+    (GamepadButton::ButtonRightBumper, "PadRightBumper"),
+    (GamepadButton::ButtonBack, "PadBack"),
+    (GamepadButton::ButtonStart, "PadStart"),
+    (GamepadButton::ButtonGuide, "PadGuide"),
+    (GamepadButton::ButtonLeftThumb, "PadLeftThumb"),
+    (GamepadButton::ButtonRightThumb, "PadRightThumb"),
+    (GamepadButton::ButtonDpadUp, "PadUp"),
+    (GamepadButton::ButtonDpadRight, "PadRight"),
+    (GamepadButton::ButtonDpadDown, "PadDown"),
+    (GamepadButton::ButtonDpadLeft, "PadLeft"),
+];
+
+pub fn key_name(key: Key) -> Option<&'static str> {
+    KEY_NAMES.iter().find(|(k, _)| *k == key).map(|(_, n)| *n)
+}
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    KEY_NAMES.iter().find(|(_, n)| *n == name).map(|(k, _)| *k)
+}
+
+pub fn button_name(button: GamepadButton) -> Option<&'static str> {
+    BUTTON_NAMES
+        .iter()
+        .find(|(b, _)| *b == button)
+        .map(|(_, n)| *n)
+}
+
+pub fn button_from_name(name: &str) -> Option<GamepadButton> {
+    BUTTON_NAMES
+        .iter()
+        .find(|(_, n)| *n == name)
+        .map(|(b, _)| *b)
 }
 
 impl GameAction {
@@ -22,47 +133,216 @@ impl GameAction {
         }
     }
 
+    // Key of the action's name in the string tables
+    pub fn text_key(self) -> &'static str {
+        match self {
+            Self::Left => "action.left",
+            Self::Right => "action.right",
+            Self::Down => "action.down",
+            Self::Up => "action.up",
+            Self::Enter => "action.enter",
+            Self::Back => "action.back",
+        }
+    }
+
     pub fn into_menu_action(self) -> Option<MenuAction> {
         match self {
             Self::Up => Some(MenuAction::Up),
             Self::Down => Some(MenuAction::Down),
+            Self::Left => Some(MenuAction::Left),
+            Self::Right => Some(MenuAction::Right),
             Self::Enter => Some(MenuAction::Select),
-            _ => None,
+            Self::Back => Some(MenuAction::Back),
         }
     }
 }
 
+type KeyMap = &'static [(Key, GameAction)];
+
+const ARROWS: KeyMap = &[
+    (Key::Up, GameAction::Up),
+    (Key::Left, GameAction::Left),
+    (Key::Down, GameAction::Down),
+    (Key::Right, GameAction::Right),
+];
+
+const WASD: KeyMap = &[
+    (Key::W, GameAction::Up),
+    (Key::A, GameAction::Left),
+    (Key::S, GameAction::Down),
+    (Key::D, GameAction::Right),
+];
+
+const IJKL: KeyMap = &[
+    (Key::I, GameAction::Up),
+    (Key::J, GameAction::Left),
+    (Key::K, GameAction::Down),
+    (Key::L, GameAction::Right),
+];
+
+const CONFIRM: KeyMap = &[
+    (Key::Enter, GameAction::Enter),
+    (Key::Escape, GameAction::Back),
+];
+
+// Everything on the keypad, so the game can be played with one hand
+const KEYPAD: KeyMap = &[
+    (Key::Kp8, GameAction::Up),
+    (Key::Kp4, GameAction::Left),
+    (Key::Kp2, GameAction::Down),
+    (Key::Kp6, GameAction::Right),
+    (Key::KpEnter, GameAction::Enter),
+    (Key::Kp0, GameAction::Back),
+];
+
+// Shared by every profile, shoulder buttons chop like the d-pad
+const PAD_BUTTONS: &[(GamepadButton, GameAction)] = &[
+    (GamepadButton::ButtonDpadUp, GameAction::Up),
+    (GamepadButton::ButtonDpadLeft, GameAction::Left),
+    (GamepadButton::ButtonDpadDown, GameAction::Down),
+    (GamepadButton::ButtonDpadRight, GameAction::Right),
+    (GamepadButton::ButtonLeftBumper, GameAction::Left),
+    (GamepadButton::ButtonRightBumper, GameAction::Right),
+    (GamepadButton::ButtonA, GameAction::Enter),
+    (GamepadButton::ButtonStart, GameAction::Enter),
+    (GamepadButton::ButtonB, GameAction::Back),
+];
+
+// Presets with the key of their name in the string tables, the first one is
+// the default
+pub const PROFILES: &[(&str, &[KeyMap])] = &[
+    ("profile.default", &[ARROWS, WASD, IJKL, CONFIRM]),
+    ("profile.arrows", &[ARROWS, CONFIRM]),
+    ("profile.wasd", &[WASD, CONFIRM]),
+    ("profile.ijkl", &[IJKL, CONFIRM]),
+    ("profile.one_handed", &[KEYPAD]),
+];
+
+// Actions that can be rebound from the controls screen. Back stays on escape
+// so that the screen can always be left.
+pub const REBINDABLE: [GameAction; 5] = [
+    GameAction::Left,
+    GameAction::Right,
+    GameAction::Up,
+    GameAction::Down,
+    GameAction::Enter,
+];
+
 pub struct Controls {
     bindings: HashMap<Key, GameAction>,
+    // Few enough that a list is simpler than a map
+    buttons: Vec<(GamepadButton, GameAction)>,
 }
 
 impl Default for Controls {
     fn default() -> Self {
+        Self::profile(0)
+    }
+}
+
+impl Controls {
+    pub fn new(bindings: HashMap<Key, GameAction>) -> Self {
+        Self {
+            bindings,
+            buttons: PAD_BUTTONS.to_vec(),
+        }
+    }
+
+    pub fn profile(idx: usize) -> Self {
+        let (_, maps) = PROFILES[idx];
+        Self::new(maps.iter().flat_map(|map| map.iter().copied()).collect())
+    }
+
+    // Unknown names are skipped with a warning. Files without any gamepad
+    // button keep the default ones.
+    pub fn from_names(names: &BTreeMap<String, GameAction>) -> Self {
         let mut bindings = HashMap::new();
-        bindings.insert(Key::Up, GameAction::Up);
-        bindings.insert(Key::Left, GameAction::Left);
-        bindings.insert(Key::Down, GameAction::Down);
-        bindings.insert(Key::Right, GameAction::Right);
+        let mut buttons = vec![];
+        for (name, &action) in names {
+            if let Some(key) = key_from_name(name) {
+                bindings.insert(key, action);
+            } else if let Some(button) = button_from_name(name) {
+                buttons.push((button, action));
+            } else {
+                eprintln!("unknown key in bindings: {}", name);
+            }
+        }
+        let mut controls = Self::new(bindings);
+        if !buttons.is_empty() {
+            controls.buttons = buttons;
+        }
+        controls
+    }
 
-        bindings.insert(Key::W, GameAction::Up);
-        bindings.insert(Key::A, GameAction::Left);
-        bindings.insert(Key::S, GameAction::Down);
-        bindings.insert(Key::D, GameAction::Right);
+    pub fn to_names(&self) -> BTreeMap<String, GameAction> {
+        let keys = self
+            .bindings
+            .iter()
+            .filter_map(|(&key, &action)| key_name(key).map(|n| (n.to_string(), action)));
+        let buttons = self
+            .buttons
+            .iter()
+            .filter_map(|&(button, action)| button_name(button).map(|n| (n.to_string(), action)));
+        keys.chain(buttons).collect()
+    }
 
-        bindings.insert(Key::I, GameAction::Up);
-        bindings.insert(Key::J, GameAction::Left);
-        bindings.insert(Key::K, GameAction::Down);
-        bindings.insert(Key::L, GameAction::Right);
+    // Names of the keys then buttons bound to the action, sorted so that they
+    // display consistently
+    pub fn names_for(&self, action: GameAction) -> Vec<&'static str> {
+        let mut keys: Vec<_> = self
+            .bindings
+            .iter()
+            .filter(|(_, &a)| a == action)
+            .filter_map(|(&k, _)| key_name(k))
+            .collect();
+        keys.sort_unstable();
+        let mut buttons: Vec<_> = self
+            .buttons
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .filter_map(|&(b, _)| button_name(b))
+            .collect();
+        buttons.sort_unstable();
+        keys.extend(buttons);
+        keys
+    }
 
-        bindings.insert(Key::Enter, GameAction::Enter);
+    // Makes `key` the only key for `action`. Fails with the action the key is
+    // already used for, leaving the bindings untouched.
+    pub fn rebind(&mut self, action: GameAction, key: Key) -> Result<(), GameAction> {
+        match self.bindings.get(&key) {
+            Some(&other) if other != action => return Err(other),
+            _ => (),
+        }
+        self.bindings.retain(|_, &mut a| a != action);
+        self.bindings.insert(key, action);
+        Ok(())
+    }
 
-        Self::new(bindings)
+    // Same as `rebind` for gamepad buttons, keys stay as they are
+    pub fn rebind_button(
+        &mut self,
+        action: GameAction,
+        button: GamepadButton,
+    ) -> Result<(), GameAction> {
+        match self.buttons.iter().find(|(b, _)| *b == button) {
+            Some(&(_, other)) if other != action => return Err(other),
+            _ => (),
+        }
+        self.buttons.retain(|(_, a)| *a != action);
+        self.buttons.push((button, action));
+        Ok(())
     }
-}
 
-impl Controls {
-    pub fn new(bindings: HashMap<Key, GameAction>) -> Self {
-        Self { bindings }
+    pub fn convert_pad(&self, input: PadInput) -> Option<GameAction> {
+        match input {
+            PadInput::Button(button) => self
+                .buttons
+                .iter()
+                .find(|(b, _)| *b == button)
+                .map(|&(_, a)| a),
+            PadInput::Stick(action) => Some(action),
+        }
     }
 
     pub fn convert(&self, event: WindowEvent) -> Option<GameAction> {
@@ -73,3 +353,62 @@ impl Controls {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys_for(controls: &Controls, action: GameAction) -> Vec<Key> {
+        controls
+            .bindings
+            .iter()
+            .filter(|(_, &a)| a == action)
+            .map(|(&k, _)| k)
+            .collect()
+    }
+
+    #[test]
+    fn rebind_replaces_every_key_of_the_action() {
+        let mut controls = Controls::profile(0);
+        assert_eq!(controls.rebind(GameAction::Left, Key::Z), Ok(()));
+        assert_eq!(keys_for(&controls, GameAction::Left), vec![Key::Z]);
+        assert_eq!(controls.bindings.get(&Key::A), None);
+    }
+
+    #[test]
+    fn rebind_to_a_used_key_fails() {
+        let mut controls = Controls::profile(0);
+        let before = controls.to_names();
+        assert_eq!(
+            controls.rebind(GameAction::Left, Key::D),
+            Err(GameAction::Right)
+        );
+        assert_eq!(controls.to_names(), before);
+    }
+
+    #[test]
+    fn rebind_to_own_key_keeps_only_that_key() {
+        let mut controls = Controls::profile(0);
+        assert_eq!(controls.rebind(GameAction::Up, Key::W), Ok(()));
+        assert_eq!(keys_for(&controls, GameAction::Up), vec![Key::W]);
+    }
+
+    #[test]
+    fn rebind_button_conflicts_with_other_action() {
+        let mut controls = Controls::profile(0);
+        assert_eq!(
+            controls.rebind_button(GameAction::Left, GamepadButton::ButtonB),
+            Err(GameAction::Back)
+        );
+        assert_eq!(
+            controls.rebind_button(GameAction::Back, GamepadButton::ButtonX),
+            Ok(())
+        );
+        assert_eq!(
+            controls.convert_pad(PadInput::Button(GamepadButton::ButtonB)),
+            None
+        );
+        // Keys are not affected by button bindings
+        assert_eq!(keys_for(&controls, GameAction::Back), vec![Key::Escape]);
+    }
+}
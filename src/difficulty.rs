@@ -0,0 +1,131 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    assets,
+    error::{Error, Result},
+    game::{Branch, GameMode},
+};
+
+pub const DIFFICULTY_PATH: &str = "data/difficulty.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DifficultyLevel {
+    // Score from which this level applies
+    pub from_score: u32,
+    // Chance of a branch on a segment following an empty one
+    pub branch_probability: f64,
+    // A branch is forced after this many empty segments in a row
+    pub max_empty_run: u32,
+    // Chance of a branch directly following another on the same side
+    pub same_side_probability: f64,
+    pub tree_height: usize,
+}
+
+impl DifficultyLevel {
+    // Branches on opposite sides are never adjacent, so there is always a way through
+    pub fn next_branch<R: Rng + ?Sized>(
+        &self,
+        last: Branch,
+        empty_run: u32,
+        rng: &mut R,
+    ) -> Branch {
+        match last {
+            Branch::None => {
+                if empty_run >= self.max_empty_run || rng.gen_bool(self.branch_probability) {
+                    rng.gen()
+                } else {
+                    Branch::None
+                }
+            }
+            side => {
+                if rng.gen_bool(self.same_side_probability) {
+                    side
+                } else {
+                    Branch::None
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DifficultyProfile {
+    // Sorted by `from_score`, the first level should start at 0
+    pub levels: Vec<DifficultyLevel>,
+}
+
+impl DifficultyProfile {
+    // A branch after every empty segment, always alternating with empty ones
+    pub fn classic() -> Self {
+        Self {
+            levels: vec![DifficultyLevel {
+                from_score: 0,
+                branch_probability: 1.,
+                max_empty_run: 1,
+                same_side_probability: 0.,
+                tree_height: 5,
+            }],
+        }
+    }
+
+    pub fn level(&self, score: u32) -> &DifficultyLevel {
+        self.levels
+            .iter()
+            .rev()
+            .find(|l| l.from_score <= score)
+            .or_else(|| self.levels.first())
+            .expect("Difficulty profile without levels")
+    }
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.levels.is_empty() {
+            return Err("profile has no levels".to_string());
+        }
+        for l in &self.levels {
+            let probabilities = [l.branch_probability, l.same_side_probability];
+            if probabilities.iter().any(|p| !(0. ..=1.).contains(p)) {
+                return Err("probabilities must be between 0 and 1".to_string());
+            }
+            if l.tree_height < 2 {
+                return Err("tree height must be at least 2".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Difficulties {
+    profiles: HashMap<GameMode, DifficultyProfile>,
+}
+
+impl Difficulties {
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut profiles: HashMap<GameMode, DifficultyProfile> = assets::load_json(path)?;
+        for (mode, profile) in &mut profiles {
+            profile
+                .validate()
+                .map_err(|e| Error::Invalid(path.to_path_buf(), format!("{:?}: {}", mode, e)))?;
+            profile.levels.sort_by_key(|l| l.from_score);
+        }
+        Ok(Self { profiles })
+    }
+
+    // Falls back to the classic profile for every mode if the file cannot be used
+    pub fn load_or_default() -> Self {
+        Self::load(Path::new(DIFFICULTY_PATH)).unwrap_or_else(|e| {
+            eprintln!("{}\nusing the classic difficulty", e);
+            Self {
+                profiles: HashMap::new(),
+            }
+        })
+    }
+
+    pub fn profile(&self, mode: GameMode) -> DifficultyProfile {
+        self.profiles
+            .get(&mode)
+            .cloned()
+            .unwrap_or_else(DifficultyProfile::classic)
+    }
+}
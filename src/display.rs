@@ -0,0 +1,59 @@
+use glfw::{Glfw, Monitor, SwapInterval, Window};
+
+use crate::settings::{DisplaySettings, WindowMode};
+
+// Names of the connected monitors, in the order used by `DisplaySettings::monitor`.
+// None for monitors without a name, which the menu numbers instead.
+pub fn monitor_names(glfw: &mut Glfw) -> Vec<Option<String>> {
+    glfw.with_connected_monitors(|_, monitors| monitors.iter().map(Monitor::get_name).collect())
+}
+
+// Top left corner of a window of the given size centered on the monitor
+fn centered(monitor: &Monitor, width: u32, height: u32) -> (i32, i32) {
+    let (x, y) = monitor.get_pos();
+    match monitor.get_video_mode() {
+        Some(mode) => (
+            x + (mode.width as i32 - width as i32).max(0) / 2,
+            y + (mode.height as i32 - height as i32).max(0) / 2,
+        ),
+        None => (x, y),
+    }
+}
+
+// Switches the window to the display settings, can be called at any time.
+// A monitor that is no longer connected falls back to the primary one.
+pub fn apply(window: &mut Window, display: &DisplaySettings) {
+    let mut glfw = window.glfw.clone();
+    glfw.with_connected_monitors_mut(|glfw, monitors| {
+        let monitor = monitors.get(display.monitor).or_else(|| monitors.first());
+        let (width, height) = (display.width, display.height);
+        match (display.window_mode, monitor) {
+            (WindowMode::Fullscreen, Some(monitor)) => {
+                window.set_decorated(true);
+                let rate = monitor.get_video_mode().map(|mode| mode.refresh_rate);
+                let mode = glfw::WindowMode::FullScreen(monitor);
+                window.set_monitor(mode, 0, 0, width, height, rate);
+            }
+            (WindowMode::Borderless, Some(monitor)) => {
+                let (x, y) = monitor.get_pos();
+                let (width, height) = monitor
+                    .get_video_mode()
+                    .map_or((width, height), |mode| (mode.width, mode.height));
+                window.set_decorated(false);
+                window.set_monitor(glfw::WindowMode::Windowed, x, y, width, height, None);
+            }
+            // Without any monitor there is nothing else to do
+            (_, monitor) => {
+                let (x, y) = monitor.map_or((0, 0), |m| centered(m, width, height));
+                window.set_decorated(true);
+                window.set_monitor(glfw::WindowMode::Windowed, x, y, width, height, None);
+            }
+        }
+        // Applies to the current context, which is the window's
+        glfw.set_swap_interval(if display.vsync {
+            SwapInterval::Sync(1)
+        } else {
+            SwapInterval::None
+        });
+    });
+}
@@ -0,0 +1,96 @@
+use luminance::{
+    shader::ProgramError,
+    tess::{TessError, TessMapError},
+    texture::TextureError,
+};
+use rodio::{decoder::DecoderError, PlayError, StreamError};
+use std::{fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum Error {
+    Io(PathBuf, io::Error),
+    Image(PathBuf, image::ImageError),
+    Audio(PathBuf, DecoderError),
+    Json(PathBuf, serde_json::Error),
+    Invalid(PathBuf, String),
+    AudioDevice(StreamError),
+    AudioPlayback(PlayError),
+    Texture(TextureError),
+    Shader(ProgramError),
+    Tess(TessError),
+    TessMap(TessMapError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "cannot open {}: {}", path.display(), e),
+            Self::Image(path, e) => write!(f, "cannot decode image {}: {}", path.display(), e),
+            Self::Audio(path, e) => write!(f, "cannot decode audio {}: {}", path.display(), e),
+            Self::Json(path, e) => write!(f, "cannot parse {}: {}", path.display(), e),
+            Self::Invalid(path, reason) => write!(f, "invalid {}: {}", path.display(), reason),
+            Self::AudioDevice(e) => write!(f, "cannot open audio device: {}", e),
+            Self::AudioPlayback(e) => write!(f, "cannot play audio: {}", e),
+            Self::Texture(e) => write!(f, "cannot create texture: {}", e),
+            Self::Shader(e) => write!(f, "cannot build shader program: {}", e),
+            Self::Tess(e) => write!(f, "cannot create mesh: {}", e),
+            Self::TessMap(e) => write!(f, "cannot update mesh: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Image(_, e) => Some(e),
+            Self::Audio(_, e) => Some(e),
+            Self::Json(_, e) => Some(e),
+            Self::AudioDevice(e) => Some(e),
+            Self::AudioPlayback(e) => Some(e),
+            Self::Invalid(..)
+            | Self::Texture(_)
+            | Self::Shader(_)
+            | Self::Tess(_)
+            | Self::TessMap(_) => None,
+        }
+    }
+}
+
+impl From<TextureError> for Error {
+    fn from(e: TextureError) -> Self {
+        Self::Texture(e)
+    }
+}
+
+impl From<ProgramError> for Error {
+    fn from(e: ProgramError) -> Self {
+        Self::Shader(e)
+    }
+}
+
+impl From<TessError> for Error {
+    fn from(e: TessError) -> Self {
+        Self::Tess(e)
+    }
+}
+
+impl From<TessMapError> for Error {
+    fn from(e: TessMapError) -> Self {
+        Self::TessMap(e)
+    }
+}
+
+impl From<StreamError> for Error {
+    fn from(e: StreamError) -> Self {
+        Self::AudioDevice(e)
+    }
+}
+
+impl From<PlayError> for Error {
+    fn from(e: PlayError) -> Self {
+        Self::AudioPlayback(e)
+    }
+}
@@ -0,0 +1,112 @@
+use rusttype::{Font, GlyphId, Scale};
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{
+    assets::FontAssets,
+    error::{Error, Result},
+};
+
+const EXTENSIONS: [&str; 2] = ["ttf", "otf"];
+
+// What the text is used for, each kind can have its own fonts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FontRole {
+    Body,
+    Title,
+    Score,
+}
+
+impl FontRole {
+    pub const ALL: [FontRole; 3] = [FontRole::Body, FontRole::Title, FontRole::Score];
+}
+
+// A glyph of one of the loaded fonts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlyphKey {
+    pub font: usize,
+    pub id: GlyphId,
+}
+
+// Finds the font file with the name in `dir`, whatever its extension
+fn load_font(dir: &Path, name: &str) -> Result<Font<'static>> {
+    let path = EXTENSIONS
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.exists())
+        .ok_or_else(|| {
+            let reason = "no .ttf or .otf file".to_string();
+            Error::Invalid(dir.join(name), reason)
+        })?;
+    let data = fs::read(&path).map_err(|e| Error::Io(path.clone(), e))?;
+    Font::try_from_vec(data).ok_or_else(|| Error::Invalid(path, "not a font".to_string()))
+}
+
+// Every font named in the manifest, each parsed once. Each role has a chain of
+// fonts, characters come from the first one that has them.
+pub struct Fonts {
+    fonts: Vec<Font<'static>>,
+    chains: HashMap<FontRole, Vec<usize>>,
+}
+
+impl Fonts {
+    // Fonts that cannot be loaded are skipped, but every role needs at least one
+    pub fn load(assets: &FontAssets) -> Result<Self> {
+        let mut fonts = vec![];
+        let mut loaded: HashMap<&str, Option<usize>> = HashMap::new();
+        let mut chains = HashMap::new();
+        for &role in &FontRole::ALL {
+            let mut chain = vec![];
+            for name in assets.names(role) {
+                let idx = *loaded.entry(name.as_str()).or_insert_with(|| {
+                    match load_font(&assets.dir, name) {
+                        Ok(font) => {
+                            fonts.push(font);
+                            Some(fonts.len() - 1)
+                        }
+                        Err(e) => {
+                            eprintln!("{}\nskipping font {}", e, name);
+                            None
+                        }
+                    }
+                });
+                chain.extend(idx);
+            }
+            if chain.is_empty() {
+                let reason = format!("no usable font for {:?} text", role);
+                return Err(Error::Invalid(assets.dir.clone(), reason));
+            }
+            chains.insert(role, chain);
+        }
+        Ok(Self { fonts, chains })
+    }
+
+    pub fn font(&self, idx: usize) -> &Font<'static> {
+        &self.fonts[idx]
+    }
+
+    // The replacement glyph of the first font is used if no font has the character
+    pub fn glyph(&self, role: FontRole, c: char) -> GlyphKey {
+        let chain = &self.chains[&role];
+        chain
+            .iter()
+            .map(|&font| GlyphKey {
+                font,
+                id: self.fonts[font].glyph(c).id(),
+            })
+            .find(|key| key.id != GlyphId(0))
+            .unwrap_or(GlyphKey {
+                font: chain[0],
+                id: GlyphId(0),
+            })
+    }
+
+    // Height of the capital letters of the main font of the role, relative to
+    // its line height. Text sizes are given as this height.
+    pub fn cap_height(&self, role: FontRole) -> f32 {
+        self.fonts[self.chains[&role][0]]
+            .glyph('H')
+            .scaled(Scale::uniform(1.))
+            .exact_bounding_box()
+            .map_or(0.7, |rect| -rect.min.y)
+    }
+}
@@ -1,6 +1,18 @@
 use rand::distributions::{Distribution, Standard};
-use rand::Rng;
-use std::collections::VecDeque;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, str::FromStr, time::Duration};
+
+use crate::difficulty::DifficultyProfile;
+
+const MAX_TIME: f32 = 6.;
+const START_TIME: f32 = 3.;
+const CHOP_BONUS: f32 = 0.25;
+const BASE_DRAIN: f32 = 1.;
+const DRAIN_PER_POINT: f32 = 0.01;
+
+pub type GameRng = ChaCha8Rng;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Branch {
@@ -18,6 +30,29 @@ impl Distribution<Branch> for Standard {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GameMode {
+    Classic,
+    Casual,
+    Expert,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 3] = [GameMode::Classic, GameMode::Casual, GameMode::Expert];
+}
+
+impl FromStr for GameMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GameMode::ALL
+            .iter()
+            .copied()
+            .find(|m| format!("{:?}", m).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown game mode: {}", s))
+    }
+}
+
 #[derive(PartialEq, Clone, Copy)]
 pub enum PlayerPos {
     Left,
@@ -53,22 +88,60 @@ impl Player {
 pub enum GameEvent {
     Performed(PlayerAction),
     Finished(u32),
+    TimeUp(u32),
 }
 
 pub struct Game {
     player: Player,
     pub tree: VecDeque<Branch>,
+    // Segments added on top of the initial tree, the newest are at its back
+    segments_grown: u64,
+    time_left: f32,
+    seed: u64,
+    rng: GameRng,
+    profile: DifficultyProfile,
 }
 
 impl Game {
-    pub fn new() -> Self {
-        let tree: VecDeque<Branch> = vec![Branch::None; 5].into();
+    pub fn new(profile: DifficultyProfile) -> Self {
+        Self::with_seed(profile, rand::random())
+    }
+
+    pub fn with_seed(profile: DifficultyProfile, seed: u64) -> Self {
+        let height = profile.level(0).tree_height;
+        let tree: VecDeque<Branch> = vec![Branch::None; height].into();
         let player = Player {
             pos: PlayerPos::Left,
             alive: true,
             score: 0,
         };
-        Self { player, tree }
+        Self {
+            player,
+            tree,
+            segments_grown: 0,
+            time_left: START_TIME,
+            seed,
+            rng: GameRng::seed_from_u64(seed),
+            profile,
+        }
+    }
+
+    // Grows the tree up to the current level's height, or lets it shrink by
+    // not replacing the chopped segment
+    fn grow_tree(&mut self) {
+        let level = self.profile.level(self.player.score);
+        while self.tree.len() < level.tree_height.max(2) {
+            let last = *self.tree.back().unwrap();
+            let empty_run = self
+                .tree
+                .iter()
+                .rev()
+                .take_while(|&&b| b == Branch::None)
+                .count() as u32;
+            let branch = level.next_branch(last, empty_run, &mut self.rng);
+            self.tree.push_back(branch);
+            self.segments_grown += 1;
+        }
     }
 
     pub fn update(&mut self, action: PlayerAction) -> GameEvent {
@@ -79,17 +152,13 @@ impl Game {
         } else {
             self.tree.pop_front();
             self.player.score += 1;
+            self.grow_tree();
             let lowest_branch = self.tree.front().unwrap();
             if self.player.collides_with(lowest_branch) {
                 self.player.alive = false;
             }
 
-            let new_branch = if *self.tree.back().unwrap() == Branch::None {
-                rand::random::<Branch>()
-            } else {
-                Branch::None
-            };
-            self.tree.push_back(new_branch);
+            self.time_left = (self.time_left + CHOP_BONUS).min(MAX_TIME);
         }
 
         if self.player.alive {
@@ -99,6 +168,38 @@ impl Game {
         }
     }
 
+    // Drains the time budget, faster the higher the score
+    pub fn tick(&mut self, dt: Duration) -> Option<GameEvent> {
+        if !self.player.alive {
+            return None;
+        }
+        let drain = BASE_DRAIN + DRAIN_PER_POINT * self.player.score as f32;
+        self.time_left -= drain * dt.as_secs_f32();
+        if self.time_left <= 0. {
+            self.time_left = 0.;
+            self.player.alive = false;
+            Some(GameEvent::TimeUp(self.player.score))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_time_fraction(&self) -> f32 {
+        self.time_left / MAX_TIME
+    }
+
+    pub fn get_profile(&self) -> &DifficultyProfile {
+        &self.profile
+    }
+
+    pub fn segments_grown(&self) -> u64 {
+        self.segments_grown
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn get_score(&self) -> u32 {
         self.player.score
     }
@@ -107,3 +208,48 @@ impl Game {
         self.player.pos
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Chops on the side with no branch on the two lowest segments
+    fn safe_action(game: &Game) -> PlayerAction {
+        if game.tree.iter().take(2).any(|&b| b == Branch::Left) {
+            PlayerAction::ChopRight
+        } else {
+            PlayerAction::ChopLeft
+        }
+    }
+
+    // Branches grown during the run, and the final score
+    fn play(seed: u64, chops: usize) -> (Vec<Branch>, u32) {
+        let mut game = Game::with_seed(DifficultyProfile::classic(), seed);
+        let mut grown = vec![];
+        for _ in 0..chops {
+            let event = game.update(safe_action(&game));
+            assert!(matches!(event, GameEvent::Performed(_)));
+            grown.extend(game.tree.back());
+        }
+        (grown, game.get_score())
+    }
+
+    #[test]
+    fn same_seed_grows_same_tree() {
+        assert_eq!(play(42, 200), play(42, 200));
+    }
+
+    #[test]
+    fn different_seeds_grow_different_trees() {
+        assert_ne!(play(1, 200).0, play(2, 200).0);
+    }
+
+    #[test]
+    fn time_runs_out() {
+        let mut game = Game::with_seed(DifficultyProfile::classic(), 0);
+        assert!(game.tick(Duration::from_secs(1)).is_none());
+        let event = game.tick(Duration::from_secs_f32(START_TIME));
+        assert!(matches!(event, Some(GameEvent::TimeUp(0))));
+        assert!(game.tick(Duration::from_secs(1)).is_none());
+    }
+}
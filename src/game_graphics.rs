@@ -1,20 +1,99 @@
 use crate::{
     animation::Animation,
+    assets::{self, AssetManifest},
+    controls::GameAction,
+    error::Result,
+    fonts::{FontRole, Fonts, GlyphKey},
     game::{Game, PlayerPos},
     geometry,
-    menu::Menu,
-    object::{Model, Object, ResourceManager, TessResource, TextureResource},
-    text, transform,
+    highscores::HighScores,
+    locale::Locale,
+    menu::{Menu, MenuTree},
+    object::{Color, Model, Object, ResourceManager, TessResource, TextureResource, WHITE},
+    renderer::{DrawList, Quad},
+    text::{self, Align, Effect, TextRun, TextStyle},
+    transform,
 };
-use image::{imageops, io::Reader};
+use image::{imageops, Rgba, RgbaImage};
 use luminance::context::GraphicsContext;
 use luminance_front::Backend;
 use rapier3d::na::{Matrix4, RealField, UnitQuaternion, Vector3};
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
 
-pub struct GameObject {
-    pub model: Model,
-    pub transform: Matrix4<f32>,
+// UI coordinates go from -1 at the bottom to 1 at the top of the window, and
+// from -aspect to aspect horizontally, so that text keeps its proportions
+#[derive(Debug, Clone, Copy)]
+pub struct UiSpace {
+    pub aspect: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Anchor {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    Center,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl UiSpace {
+    pub fn new(width: f64, height: f64) -> Self {
+        Self {
+            aspect: (width / height.max(1.)) as f32,
+        }
+    }
+
+    // Point at `offset` from the anchor, in UI coordinates
+    pub fn place(&self, anchor: Anchor, offset: (f32, f32)) -> (f32, f32) {
+        let a = self.aspect;
+        let (x, y) = match anchor {
+            Anchor::TopLeft => (-a, 1.),
+            Anchor::TopCenter => (0., 1.),
+            Anchor::TopRight => (a, 1.),
+            Anchor::Center => (0., 0.),
+            Anchor::BottomLeft => (-a, -1.),
+            Anchor::BottomCenter => (0., -1.),
+            Anchor::BottomRight => (a, -1.),
+        };
+        (x + offset.0, y + offset.1)
+    }
+
+    // Horizontal position `fraction` of the way from the center to the right
+    // edge, negative fractions are on the left
+    pub fn column(&self, fraction: f32) -> f32 {
+        fraction * self.aspect
+    }
+
+    // UI position of a point given in window coordinates from -1 to 1
+    pub fn from_window(&self, x: f32, y: f32) -> (f32, f32) {
+        (x * self.aspect, y)
+    }
+
+    pub fn to_clip(&self) -> Matrix4<f32> {
+        transform::scale2(1. / self.aspect, 1.)
+    }
+}
+
+// Screen area in UI coordinates
+#[derive(Debug, Clone, Copy)]
+pub struct Rect {
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+impl Rect {
+    fn union(self, other: Self) -> Self {
+        Self {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
 }
 
 pub struct GameResources {
@@ -24,53 +103,84 @@ pub struct GameResources {
     pub unit_quad: TessResource,
 }
 
+// Text is drawn with glyphs from an atlas texture. Glyphs that are not in the
+// atlas yet are left out and added by `update`, so they show from the next frame.
 pub struct UIResources {
-    pub char_textures: HashMap<char, TextureResource>,
-    pub unit_quad: TessResource,
-    pub start: Model,
-    pub quit: Model,
+    pub fonts: Rc<Fonts>,
+    atlas: text::GlyphAtlas,
+    atlas_texture: TextureResource,
+    missing_glyphs: RefCell<HashSet<GlyphKey>>,
+    pub bar_background: TextureResource,
+    pub bar_fill: TextureResource,
+    pub dim: TextureResource,
 }
 
 impl UIResources {
-    fn char_to_texture(
-        rm: &mut ResourceManager,
-        ctxt: &mut impl GraphicsContext<Backend = Backend>,
-        c: char,
-    ) -> TextureResource {
-        let img = text::make_char(c);
-        rm.make_texture(ctxt, &img)
-    }
-
     pub fn new(
         rm: &mut ResourceManager,
         ctxt: &mut impl GraphicsContext<Backend = Backend>,
-    ) -> Self {
-        let unit_quad = rm.make_tess(ctxt, geometry::quad(1., 1.));
+        fonts: Rc<Fonts>,
+    ) -> Result<Self> {
+        let atlas = text::GlyphAtlas::new();
+        let atlas_texture = rm.make_texture(ctxt, atlas.image())?;
 
-        let char_textures = (b'0'..=b'z')
-            .map(|c| c as char)
-            .map(|c| (c, Self::char_to_texture(rm, ctxt, c)))
-            .collect();
+        let bar_background =
+            rm.make_texture(ctxt, &RgbaImage::from_pixel(1, 1, Rgba([40, 20, 10, 180])))?;
+        let bar_fill =
+            rm.make_texture(ctxt, &RgbaImage::from_pixel(1, 1, Rgba([220, 40, 20, 255])))?;
+        let dim = rm.make_texture(ctxt, &RgbaImage::from_pixel(1, 1, Rgba([0, 0, 0, 160])))?;
 
-        let start_txt = rm.make_texture(ctxt, &text::make_text("Start"));
-        let start = vec![Object {
-            tess: unit_quad,
-            texture: start_txt,
-            transform: transform::scale2(0.8, 0.3),
-        }];
+        let mut resources = Self {
+            fonts,
+            atlas,
+            atlas_texture,
+            missing_glyphs: RefCell::new(HashSet::new()),
+            bar_background,
+            bar_fill,
+            dim,
+        };
+        // Printable ASCII is always there, so most text shows on its first frame
+        let fonts = &resources.fonts;
+        let keys: Vec<_> = FontRole::ALL
+            .iter()
+            .flat_map(|&role| (' '..='~').map(move |c| fonts.glyph(role, c)))
+            .collect();
+        resources.missing_glyphs.get_mut().extend(keys);
+        resources.update(rm)?;
+        Ok(resources)
+    }
 
-        let quit_txt = rm.make_texture(ctxt, &text::make_text("Quit"));
-        let quit = vec![Object {
-            tess: unit_quad,
-            texture: quit_txt,
-            transform: transform::scale2(0.8, 0.3),
-        }];
+    // Adds the glyphs that were missing from the text made since the last call
+    pub fn update(&mut self, rm: &mut ResourceManager) -> Result<()> {
+        let missing: Vec<_> = self.missing_glyphs.get_mut().drain().collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        for key in missing {
+            self.atlas.insert(&self.fonts, key);
+        }
+        match self.atlas.take_changed() {
+            Some((offset, pixels)) => rm.update_texture_part(self.atlas_texture, offset, &pixels),
+            None => Ok(()),
+        }
+    }
 
-        Self {
-            char_textures,
-            unit_quad,
-            start,
-            quit,
+    fn glyph_quad(&self, key: GlyphKey, color: Color) -> Option<Quad> {
+        match self.atlas.get(key) {
+            Some(glyph) => Some(Quad {
+                texture: self.atlas_texture,
+                min: glyph.min,
+                max: glyph.max,
+                uv_min: glyph.uv_min,
+                uv_max: glyph.uv_max,
+                color,
+            }),
+            None => {
+                if !self.atlas.contains(key) {
+                    self.missing_glyphs.borrow_mut().insert(key);
+                }
+                None
+            }
         }
     }
 }
@@ -79,15 +189,12 @@ impl GameResources {
     pub fn new(
         rm: &mut ResourceManager,
         ctxt: &mut impl GraphicsContext<Backend = Backend>,
-    ) -> Self {
-        let cylinder = rm.make_tess(ctxt, geometry::cylinder(1., 0.5, 20));
+        manifest: &AssetManifest,
+    ) -> Result<Self> {
+        let cylinder = rm.make_tess(ctxt, geometry::cylinder(1., 0.5, 20))?;
 
-        let bark_img = Reader::open("textures/log_texture.png")
-            .unwrap()
-            .decode()
-            .unwrap()
-            .into_rgba8();
-        let bark = rm.make_texture(ctxt, &bark_img);
+        let bark_img = assets::load_image_or_placeholder(&manifest.textures.log);
+        let bark = rm.make_texture(ctxt, &bark_img)?;
 
         let angle: f32 = RealField::frac_pi_2();
         let log_obj = Object {
@@ -108,110 +215,365 @@ impl GameResources {
         branch.transform = transform::translation3(0.9, 0., 0.) * rot_scale;
         let branch_right: Vec<Object> = vec![log_obj, branch];
 
-        let unit_quad = rm.make_tess(ctxt, geometry::quad(1., 1.));
-        Self {
+        let unit_quad = rm.make_tess(ctxt, geometry::quad(1., 1.))?;
+        Ok(Self {
             log,
             branch_left,
             branch_right,
             unit_quad,
+        })
+    }
+}
+
+const HIGHLIGHT: Color = [1., 0.8, 0.3, 1.];
+const SHADOW: Color = [0., 0., 0., 0.6];
+
+// Directions in which an outline is offset
+const OUTLINE: [(f32, f32); 8] = [
+    (1., 0.),
+    (0.7, 0.7),
+    (0., 1.),
+    (-0.7, 0.7),
+    (-1., 0.),
+    (-0.7, -0.7),
+    (0., -1.),
+    (0.7, -0.7),
+];
+
+// Text laid out around (x, y) by `text::layout`, wrapped to `max_width` if given
+pub fn draw_text(
+    list: &mut DrawList,
+    resources: &UIResources,
+    runs: &[TextRun],
+    style: &TextStyle,
+    max_width: Option<f32>,
+    x: f32,
+    y: f32,
+) {
+    let layout = text::layout(&resources.fonts, runs, style, max_width);
+    // The effect is drawn as copies of the text behind it
+    let mut passes: Vec<((f32, f32), Option<Color>)> = match style.effect {
+        Effect::None => vec![],
+        Effect::Shadow { offset, color } => vec![(offset, Some(color))],
+        Effect::Outline { width, color } => OUTLINE
+            .iter()
+            .map(|&(dx, dy)| ((dx * width, dy * width), Some(color)))
+            .collect(),
+    };
+    passes.push(((0., 0.), None));
+    let scale = transform::scale2(layout.scale, layout.scale);
+    for &((dx, dy), color) in &passes {
+        for glyph in &layout.glyphs {
+            if let Some(quad) = resources.glyph_quad(glyph.key, color.unwrap_or(glyph.color)) {
+                let position = transform::translation2(x + glyph.x + dx, y + glyph.y + dy);
+                list.quad(&quad, &(position * scale));
+            }
         }
     }
 }
 
-fn make_text_object(
+// Area covered by the text drawn by `draw_text` with the same arguments
+pub fn text_rect(
+    fonts: &Fonts,
+    runs: &[TextRun],
+    style: &TextStyle,
+    max_width: Option<f32>,
+    x: f32,
+    y: f32,
+) -> Rect {
+    let layout = text::layout(fonts, runs, style, max_width);
+    Rect {
+        min: (x + layout.min.0, y + layout.min.1),
+        max: (x + layout.max.0, y + layout.max.1),
+    }
+}
+
+fn draw_time_bar(list: &mut DrawList, game: &Game, resources: &UIResources, ui: &UiSpace) {
+    let (width, height) = (1.8, 0.08);
+    let fill_width = width * game.get_time_fraction();
+    let (x, y) = ui.place(Anchor::TopCenter, (0., -0.5));
+    let position = transform::translation2(x, y);
+    list.quad(
+        &Quad::unit(resources.bar_background),
+        &(position * transform::scale2(width, height)),
+    );
+    list.quad(
+        &Quad::unit(resources.bar_fill),
+        &(position
+            * transform::translation2((fill_width - width) / 2., 0.)
+            * transform::scale2(fill_width, height)),
+    );
+}
+
+pub fn draw_ui(
+    list: &mut DrawList,
+    game: &Game,
     resources: &UIResources,
-    text: String,
-    height: f32,
-    width: f32,
+    ui: &UiSpace,
+    locale: &Locale,
+) {
+    let (x, y) = ui.place(Anchor::TopCenter, (0., -0.25));
+    let shadow = Effect::Shadow {
+        offset: (0.02, -0.02),
+        color: SHADOW,
+    };
+    draw_text(
+        list,
+        resources,
+        &[TextRun::new(&locale.number(game.get_score()), WHITE)],
+        &TextStyle::new(0.4).font(FontRole::Score).effect(shadow),
+        None,
+        x,
+        y,
+    );
+    draw_time_bar(list, game, resources, ui);
+}
+
+// Lines of text showing a menu item, as (text, height, x, y)
+fn menu_item_lines(
+    menu: &Menu,
+    i: usize,
+    ui: &UiSpace,
+    locale: &Locale,
+) -> Vec<(String, f32, f32, f32)> {
+    let item = &menu.items[i];
+    let height = if i == menu.selected_idx { 0.14 } else { 0.1 };
+    let y = menu.item_y(i);
+    match item.value_text(locale) {
+        Some(value) => vec![
+            (item.label.clone(), height, ui.column(-0.4), y),
+            (value, height, ui.column(0.4), y),
+        ],
+        None => vec![(item.label.clone(), height, 0., y)],
+    }
+}
+
+pub fn draw_menu(
+    list: &mut DrawList,
+    menu: &MenuTree,
+    resources: &UIResources,
+    ui: &UiSpace,
+    locale: &Locale,
+) {
+    let menu = menu.current();
+    let title_y = Menu::title_y();
+    draw_role_line(
+        list,
+        resources,
+        FontRole::Title,
+        &menu.title,
+        0.2,
+        0.,
+        title_y,
+    );
+    for i in 0..menu.items.len() {
+        for (text, height, x, y) in menu_item_lines(menu, i, ui, locale) {
+            draw_line(list, resources, &text, height, x, y);
+        }
+    }
+    if let Some(note) = &menu.note {
+        let (x, y) = ui.place(Anchor::BottomCenter, (0., 0.1));
+        draw_line(list, resources, note, 0.07, x, y);
+    }
+}
+
+// The item drawn under the point, using the same transforms as `draw_menu`
+pub fn menu_item_at(
+    menu: &Menu,
+    fonts: &Fonts,
+    ui: &UiSpace,
+    locale: &Locale,
     x: f32,
     y: f32,
-) -> GameObject {
-    let model = text
-        .chars()
-        .enumerate()
-        .map(|(i, c)| Object {
-            tess: resources.unit_quad,
-            texture: *resources.char_textures.get(&c).unwrap(),
-            transform: transform::translation3(i as f32, 0., 0.),
-        })
-        .collect();
-    let length = text.len() as f32;
-    let centering = transform::translation2(0.5 - length / 2., 0.);
-    let scale = transform::scale2(width / length, height);
-    GameObject {
-        model,
-        transform: transform::translation2(x, y) * scale * centering,
-    }
-}
-
-pub fn make_ui(game: &Game, resources: &UIResources) -> Vec<GameObject> {
-    let text = game.get_score().to_string();
-    let len = text.len() as f32;
-    let score = make_text_object(resources, text, 0.4, 0.2 * len, 0., 0.75);
-    vec![score]
-}
-
-pub fn make_menu(menu: &Menu, resources: &UIResources) -> Vec<GameObject> {
-    let buttons = [resources.start.clone(), resources.quit.clone()];
-    let selected = menu.selected_idx;
-    let start_pos = 0.5;
-    buttons
-        .iter()
-        .enumerate()
-        .map(|(i, m)| GameObject {
-            model: m.clone(),
-            transform: {
-                let mut transform = transform::translation3(0., start_pos - i as f32, 0.);
-                if i == selected {
-                    transform *= transform::scale2(1.2, 1.2);
-                }
-                transform
-            },
-        })
-        .collect()
+) -> Option<usize> {
+    (0..menu.items.len()).find(|&i| {
+        menu_item_lines(menu, i, ui, locale)
+            .iter()
+            .map(|(text, height, line_x, line_y)| line_rect(fonts, text, *height, *line_x, *line_y))
+            .reduce(Rect::union)
+            .is_some_and(|rect| rect.contains(x, y))
+    })
+}
+
+// Darkens everything drawn before it
+pub fn draw_dim_overlay(list: &mut DrawList, resources: &UIResources, ui: &UiSpace) {
+    list.quad(
+        &Quad::unit(resources.dim),
+        &transform::scale2(2. * ui.aspect, 2.),
+    );
 }
 
-pub fn make_player(game: &Game, resources: &GameResources, chop: &Animation) -> GameObject {
+pub fn draw_player(list: &mut DrawList, game: &Game, resources: &GameResources, chop: &Animation) {
     let mut pos_x = -1.1;
     let mut transform = transform::scale3(1.2, 1.2, 1.);
     if game.get_player_pos() == PlayerPos::Right {
         pos_x *= -1.;
         transform *= transform::reflect_x();
     }
-    GameObject {
-        model: vec![Object {
-            tess: resources.unit_quad,
-            texture: chop.get_current_texture(),
-            transform,
-        }],
-        transform: transform::translation2(pos_x, 0.5),
-    }
+    let sprite = Object {
+        tess: resources.unit_quad,
+        texture: chop.get_current_texture(),
+        transform,
+    };
+    list.sprite(&sprite, &transform::translation2(pos_x, 0.5));
 }
 
+// Covers the whole window, in clip space
 pub fn make_background(
     rm: &mut ResourceManager,
     ctxt: &mut impl GraphicsContext<Backend = Backend>,
-) -> Object {
-    let img = Reader::open("textures/forest-background.jpg")
-        .unwrap()
-        .decode()
-        .unwrap()
-        .into_rgba8();
-    let texture = rm.make_texture(ctxt, &imageops::flip_vertical(&img));
-    let tess = rm.make_tess(ctxt, geometry::quad(2., 2.));
-    Object {
-        tess,
-        texture,
-        transform: transform::translation3(0., 0., -1.),
-    }
-}
-
-pub fn make_game_over_ui(game: &Game, resources: &UIResources) -> Vec<GameObject> {
-    let text = game.get_score().to_string();
-    let len = text.len() as f32;
-    let score = make_text_object(resources, text, 0.4, 0.2 * len, 0., 0.);
-    let text = "SCORE".to_string();
-    let len = text.len() as f32;
-    let text = make_text_object(resources, text, 0.4, 0.2 * len, 0., 0.5);
-    vec![text, score]
+    manifest: &AssetManifest,
+) -> Result<Quad> {
+    let img = assets::load_image_or_placeholder(&manifest.textures.background);
+    let texture = rm.make_texture(ctxt, &imageops::flip_vertical(&img))?;
+    Ok(Quad {
+        min: [-1., -1.],
+        max: [1., 1.],
+        ..Quad::unit(texture)
+    })
+}
+
+pub fn draw_game_over_ui(
+    list: &mut DrawList,
+    game: &Game,
+    resources: &UIResources,
+    ui: &UiSpace,
+    locale: &Locale,
+) {
+    let title = locale.get("game_over.score");
+    let (x, y) = ui.place(Anchor::Center, (0., 0.5));
+    draw_role_line(list, resources, FontRole::Title, title, 0.4, x, y);
+    let score = locale.number(game.get_score());
+    let (x, y) = ui.place(Anchor::Center, (0., 0.));
+    draw_role_line(list, resources, FontRole::Score, &score, 0.4, x, y);
+    let logs = locale.plural("game_over.logs", game.get_score());
+    let (x, y) = ui.place(Anchor::Center, (0., -0.4));
+    draw_line(list, resources, &logs, 0.1, x, y);
+}
+
+fn line_rect(fonts: &Fonts, text: &str, height: f32, x: f32, y: f32) -> Rect {
+    text_rect(
+        fonts,
+        &[TextRun::new(text, WHITE)],
+        &TextStyle::new(height),
+        None,
+        x,
+        y,
+    )
+}
+
+// A single line of text centered on (x, y), `height` being the height of capitals
+fn draw_line(
+    list: &mut DrawList,
+    resources: &UIResources,
+    text: &str,
+    height: f32,
+    x: f32,
+    y: f32,
+) {
+    draw_role_line(list, resources, FontRole::Body, text, height, x, y)
+}
+
+fn draw_role_line(
+    list: &mut DrawList,
+    resources: &UIResources,
+    role: FontRole,
+    text: &str,
+    height: f32,
+    x: f32,
+    y: f32,
+) {
+    draw_text(
+        list,
+        resources,
+        &[TextRun::new(text, WHITE)],
+        &TextStyle::new(height).font(role),
+        None,
+        x,
+        y,
+    )
+}
+
+pub fn draw_name_entry_ui(
+    list: &mut DrawList,
+    name: &str,
+    resources: &UIResources,
+    ui: &UiSpace,
+    locale: &Locale,
+) {
+    let title = locale.get("name_entry.title");
+    let (x, y) = ui.place(Anchor::Center, (0., 0.5));
+    draw_role_line(list, resources, FontRole::Title, title, 0.2, x, y);
+    let (x, y) = ui.place(Anchor::Center, (0., 0.25));
+    draw_line(list, resources, locale.get("name_entry.prompt"), 0.12, x, y);
+    let (x, y) = ui.place(Anchor::Center, (0., -0.1));
+    draw_line(list, resources, &format!("{}_", name), 0.2, x, y);
+}
+
+pub fn draw_high_scores_ui(
+    list: &mut DrawList,
+    highscores: &HighScores,
+    highlight: Option<usize>,
+    resources: &UIResources,
+    ui: &UiSpace,
+    locale: &Locale,
+) {
+    let title = locale.get("highscores.title");
+    let (x, y) = ui.place(Anchor::TopCenter, (0., -0.2));
+    draw_role_line(list, resources, FontRole::Title, title, 0.2, x, y);
+    // Rank and score are right aligned so that their digits line up
+    let columns = [
+        (Align::Right, ui.column(-0.56)),
+        (Align::Left, ui.column(-0.48)),
+        (Align::Right, ui.column(0.31)),
+        (Align::Left, ui.column(0.42)),
+    ];
+    for (i, entry) in highscores.entries().iter().enumerate() {
+        let color = if Some(i) == highlight {
+            HIGHLIGHT
+        } else {
+            WHITE
+        };
+        let y = 0.55 - 0.14 * i as f32;
+        let cells = [
+            (i + 1).to_string(),
+            entry.name.clone(),
+            locale.number(entry.score),
+            entry.date(),
+        ];
+        for (cell, &(align, x)) in cells.iter().zip(&columns) {
+            let style = TextStyle::new(0.09).align(align);
+            let run = TextRun::new(cell, color);
+            draw_text(list, resources, &[run], &style, None, x, y);
+        }
+    }
+    if highscores.entries().is_empty() {
+        let empty = locale.get("highscores.empty");
+        let (x, y) = ui.place(Anchor::Center, (0., 0.));
+        draw_line(list, resources, empty, 0.12, x, y);
+    }
+}
+
+pub fn draw_rebind_ui(
+    list: &mut DrawList,
+    action: GameAction,
+    error: Option<&str>,
+    resources: &UIResources,
+    ui: &UiSpace,
+    locale: &Locale,
+) {
+    let (x, y) = ui.place(Anchor::Center, (0., 0.4));
+    draw_line(list, resources, locale.get("rebind.prompt"), 0.12, x, y);
+    let (x, y) = ui.place(Anchor::Center, (0., 0.15));
+    draw_line(list, resources, locale.get(action.text_key()), 0.2, x, y);
+    let (x, y) = ui.place(Anchor::BottomCenter, (0., 0.1));
+    draw_line(list, resources, locale.get("rebind.cancel"), 0.07, x, y);
+    if let Some(error) = error {
+        let style = TextStyle::new(0.09).line_spacing(1.8);
+        let run = TextRun::new(error, HIGHLIGHT);
+        // Wrapped to most of the window width, with a margin on both sides
+        let width = 2. * ui.column(0.7);
+        let (x, y) = ui.place(Anchor::Center, (0., -0.3));
+        draw_text(list, resources, &[run], &style, Some(width), x, y);
+    }
 }
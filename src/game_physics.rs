@@ -1,18 +1,25 @@
 use crate::{
-    game::{Branch, Game, PlayerAction},
-    game_graphics::{GameObject, GameResources},
+    game::{Branch, Game, GameRng, PlayerAction},
+    game_graphics::GameResources,
+    renderer::DrawList,
     transform,
 };
-use rand::distributions::Distribution;
+use rand::{distributions::Distribution, SeedableRng};
 use rapier3d::prelude::*;
 use statrs::distribution::Normal;
-use std::collections::VecDeque;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
 
 const LOG_HALF_HEIGHT: f32 = 0.5;
 const GROUND_GROUP: u32 = 0b1;
 const FLYING_GROUP: u32 = 0b10;
 const BASE_GROUP: u32 = 0b100;
 
+// Physics always advances by this much, whatever the frame rate
+pub const TIMESTEP: f32 = 1. / 60.;
+
 #[derive(Debug, Clone)]
 struct PhysicsLog {
     handle: RigidBodyHandle,
@@ -32,10 +39,19 @@ pub struct GamePhysics {
     narrow_phase: NarrowPhase,
     joints: JointSet,
     ccd_solver: CCDSolver,
+    rng: GameRng,
+    // Time not simulated yet, always less than a timestep
+    accumulator: f32,
+    // Body positions before the last step, to interpolate between steps
+    previous: HashMap<RigidBodyHandle, Isometry<Real>>,
 }
 
 impl GamePhysics {
     pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
         let mut rigid_bodies = RigidBodySet::new();
         let mut colliders = ColliderSet::new();
 
@@ -69,7 +85,10 @@ impl GamePhysics {
 
         /* Create other structures necessary for the simulation. */
         let gravity: Vector<Real> = vector![0.0, -9.81, 0.0];
-        let integration_parameters = IntegrationParameters::default();
+        let integration_parameters = IntegrationParameters {
+            dt: TIMESTEP,
+            ..IntegrationParameters::default()
+        };
         let physics_pipeline = PhysicsPipeline::new();
         let islands = IslandManager::new();
         let broad_phase = BroadPhase::new();
@@ -90,10 +109,14 @@ impl GamePhysics {
             narrow_phase,
             joints,
             ccd_solver,
+            rng: GameRng::seed_from_u64(seed),
+            accumulator: 0.,
+            previous: HashMap::new(),
         }
     }
 
-    pub fn reset(&mut self) {
+    pub fn reset(&mut self, seed: u64) {
+        self.rng = GameRng::seed_from_u64(seed);
         let body = self.rigid_bodies.get_mut(self.base_log.handle).unwrap();
         body.set_translation(vector![0., LOG_HALF_HEIGHT, 0.], true);
         body.set_linvel(vector![0., 0., 0.], true);
@@ -102,23 +125,23 @@ impl GamePhysics {
             self.remove_log(x)
         }
         self.flying_logs.clear();
+        self.accumulator = 0.;
+        self.previous.clear();
     }
 
     fn update_base_log(&mut self, branch: Branch) {
         let body = self.rigid_bodies.get_mut(self.base_log.handle).unwrap();
         body.set_translation(vector![0., 3. * LOG_HALF_HEIGHT, 0.], true);
         body.set_linvel(vector![0., -5., 0.], true);
+        // A jump, not a motion to interpolate
+        self.previous.remove(&self.base_log.handle);
         self.base_log.branch = branch;
     }
 
-    fn random_velocity(std_dev: f64) -> Vector<Real> {
-        let mut r = rand::thread_rng();
+    fn random_velocity(&mut self, std_dev: f64) -> Vector<Real> {
+        let r = &mut self.rng;
         let n = Normal::new(0.0, std_dev).unwrap();
-        vector![
-            n.sample(&mut r) as f32,
-            n.sample(&mut r) as f32,
-            n.sample(&mut r) as f32
-        ]
+        vector![n.sample(r) as f32, n.sample(r) as f32, n.sample(r) as f32]
     }
 
     pub fn add_new_flying_log(&mut self, action: PlayerAction, branch: Branch) {
@@ -126,8 +149,8 @@ impl GamePhysics {
             PlayerAction::ChopLeft => 1.,
             PlayerAction::ChopRight => -1.,
         };
-        let linvel = vector![v_x, 0.5, 0.2] * 7. + Self::random_velocity(0.4);
-        let angvel = vector![0., 0., 5. * v_x] + Self::random_velocity(0.6);
+        let linvel = vector![v_x, 0.5, 0.2] * 7. + self.random_velocity(0.4);
+        let angvel = vector![0., 0., 5. * v_x] + self.random_velocity(0.6);
 
         let log_body = RigidBodyBuilder::new_dynamic()
             .translation(vector![0., LOG_HALF_HEIGHT + 0.1, 0.])
@@ -181,7 +204,25 @@ impl GamePhysics {
         self.flying_logs.retain(|x| set.contains(x.handle));
     }
 
+    // Runs as many fixed steps as fit in the elapsed time, returning how many
+    pub fn advance(&mut self, dt: Duration) -> u32 {
+        self.accumulator += dt.as_secs_f32();
+        let mut steps = 0;
+        while self.accumulator >= TIMESTEP {
+            self.step();
+            self.accumulator -= TIMESTEP;
+            steps += 1;
+        }
+        steps
+    }
+
     pub fn step(&mut self) {
+        self.previous.clear();
+        self.previous.extend(
+            self.rigid_bodies
+                .iter()
+                .map(|(handle, body)| (handle, *body.position())),
+        );
         self.physics_pipeline.step(
             &self.gravity,
             &self.integration_parameters,
@@ -199,33 +240,77 @@ impl GamePhysics {
         //println!("Ball altitude: {}", ball_body.translation().y);
     }
 
-    pub fn make_scene(&self, game: &Game, resources: &GameResources) -> Vec<GameObject> {
+    // Position between the last two steps matching the time left in the
+    // accumulator, so that motion is smooth at any frame rate
+    fn interpolated_position(&self, handle: RigidBodyHandle) -> Isometry<Real> {
+        let current = self.rigid_bodies.get(handle).unwrap().position();
+        match self.previous.get(&handle) {
+            Some(previous) => previous.lerp_slerp(current, self.accumulator / TIMESTEP),
+            None => *current,
+        }
+    }
+
+    pub fn draw_scene(&self, list: &mut DrawList, game: &Game, resources: &GameResources) {
         let base = self
-            .rigid_bodies
-            .get(self.base_log.handle)
-            .unwrap()
-            .translation()
+            .interpolated_position(self.base_log.handle)
+            .translation
+            .vector
             .y;
-        let tree = game.tree.clone();
-        let tree = tree.iter().enumerate().map(|(i, val)| GameObject {
-            model: match val {
-                Branch::None => resources.log.clone(),
-                Branch::Left => resources.branch_left.clone(),
-                Branch::Right => resources.branch_right.clone(),
-            },
-            transform: transform::translation3(0., base + i as f32, 0.),
-        });
-        let flying = self.flying_logs.iter().map(|log| {
-            let body = self.rigid_bodies.get(log.handle).unwrap();
-            GameObject {
-                model: match log.branch {
-                    Branch::None => resources.log.clone(),
-                    Branch::Left => resources.branch_left.clone(),
-                    Branch::Right => resources.branch_right.clone(),
-                },
-                transform: body.position().to_homogeneous(),
-            }
-        });
-        tree.chain(flying).collect()
+        let model = |branch: &Branch| match branch {
+            Branch::None => &resources.log,
+            Branch::Left => &resources.branch_left,
+            Branch::Right => &resources.branch_right,
+        };
+        for (i, branch) in game.tree.iter().enumerate() {
+            let transform = transform::translation3(0., base + i as f32, 0.);
+            list.model(model(branch), &transform);
+        }
+        for log in &self.flying_logs {
+            let position = self.interpolated_position(log.handle);
+            list.model(model(&log.branch), &position.to_homogeneous());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::DifficultyProfile;
+
+    fn flying_positions(seed: u64) -> Vec<Isometry<Real>> {
+        let game = Game::with_seed(DifficultyProfile::classic(), seed);
+        let mut physics = GamePhysics::with_seed(seed);
+        for &action in &[PlayerAction::ChopLeft, PlayerAction::ChopRight] {
+            physics.update(&game, action);
+            physics.advance(Duration::from_secs_f32(10. * TIMESTEP));
+        }
+        physics
+            .flying_logs
+            .iter()
+            .map(|log| *physics.rigid_bodies.get(log.handle).unwrap().position())
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_scatters_logs_the_same() {
+        let positions = flying_positions(7);
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions, flying_positions(7));
+    }
+
+    #[test]
+    fn different_seeds_scatter_logs_differently() {
+        assert_ne!(flying_positions(7), flying_positions(8));
+    }
+
+    #[test]
+    fn moved_base_log_is_not_interpolated() {
+        let game = Game::with_seed(DifficultyProfile::classic(), 3);
+        let mut physics = GamePhysics::with_seed(3);
+        physics.advance(Duration::from_secs_f32(2.5 * TIMESTEP));
+        physics.update(&game, PlayerAction::ChopLeft);
+        let handle = physics.base_log.handle;
+        let current = *physics.rigid_bodies.get(handle).unwrap().position();
+        assert_eq!(physics.interpolated_position(handle), current);
     }
 }
@@ -1,133 +1,745 @@
-use glfw::{FlushedMessages, WindowEvent};
+use glfw::{Action, FlushedMessages, Glfw, Key, MouseButton, WindowEvent};
+use std::{rc::Rc, time::Duration};
 
 use crate::{
     animation::GameAnimations,
-    audio::{AudioPlayer, AudioResources},
-    controls::{Controls, GameAction},
-    game::{Game, GameEvent},
-    game_graphics::{self, GameObject, GameResources, UIResources},
+    audio::{AudioPlayer, AudioResources, Sound},
+    controls::{self, Controls, GameAction},
+    difficulty::Difficulties,
+    fonts::Fonts,
+    game::{Game, GameEvent, GameMode},
+    game_graphics::{self, GameResources, UIResources, UiSpace},
     game_physics::GamePhysics,
-    menu::{Menu, MenuResult},
+    gamepad::{Gamepads, PadInput},
+    highscores::{HighScore, HighScores, MAX_NAME_LEN},
+    input::{InputQueue, TimedAction},
+    locale::{self, Locale},
+    menu::{MenuCommand, MenuEvent, MenuTree},
+    options::{self, MenuContext},
+    renderer::DrawList,
+    replay::{Playback, Recorder, Replay},
+    settings::{DisplaySettings, Settings},
 };
 
+// Longer frames, e.g. while the window is dragged, only advance physics this much
+const MAX_PHYSICS_FRAME: f64 = 0.25;
+
+fn frame_time(start: f64, end: f64) -> Duration {
+    Duration::from_secs_f64((end - start).clamp(0., MAX_PHYSICS_FRAME))
+}
+
+#[derive(Clone, Copy)]
 enum GameState {
     StartMenu,
     InGame,
+    Paused,
     GameOver,
+    EnterName,
+    HighScores,
+    // Waiting for the key to bind to the action
+    Rebind(GameAction),
 }
 
 pub struct GameRunner {
     state: GameState,
-    menu: Menu,
+    menu: MenuTree,
+    pause_menu: MenuTree,
     game: Game,
     physics: GamePhysics,
     animations: GameAnimations,
     player: AudioPlayer,
-    event: Option<GameEvent>,
+    // Everything that happened in the game during the last update
+    game_events: Vec<GameEvent>,
     controls: Controls,
+    gamepads: Gamepads,
+    queue: InputQueue,
+    // GLFW time of the last update
+    last_update: Option<f64>,
+    run_time: Duration,
+    recorder: Option<Recorder>,
+    playback: Option<Playback>,
+    mode: GameMode,
+    difficulties: Difficulties,
+    highscores: HighScores,
+    name: String,
+    last_rank: Option<usize>,
+    settings: Settings,
+    // What is saved as the display settings, `settings.display` differs from
+    // it while command line overrides are in effect
+    saved_display: DisplaySettings,
+    monitors: Vec<Option<String>>,
+    // Codes and names of the languages listed in the options
+    languages: Vec<(String, String)>,
+    locale: Locale,
+    // Set when the display settings changed and the window must follow
+    display_changed: bool,
+    window_size: (f64, f64),
+    // Last cursor position in UI coordinates
+    cursor: Option<(f32, f32)>,
+    // Shared with the UI, to find the menu item under the cursor
+    fonts: Rc<Fonts>,
+    rebind_error: Option<String>,
+    // Where to go once the key is bound
+    rebind_return: GameState,
 }
 
 impl GameRunner {
-    pub fn new(animations: GameAnimations) -> Self {
+    pub fn new(
+        animations: GameAnimations,
+        settings: Settings,
+        monitors: Vec<Option<String>>,
+        fonts: Rc<Fonts>,
+    ) -> Self {
+        let mode = settings.mode;
+        let difficulties = Difficulties::load_or_default();
+        let game = Game::new(difficulties.profile(mode));
+        let physics = GamePhysics::with_seed(game.get_seed());
+        let controls = Controls::from_names(&settings.bindings);
+        let languages = locale::languages();
+        let locale = Locale::load(&settings.language);
+        let ctx = MenuContext {
+            settings: &settings,
+            controls: &controls,
+            monitors: &monitors,
+            languages: &languages,
+            locale: &locale,
+        };
+        let menu = MenuTree::new(options::main_menu(&ctx, mode));
+        let pause_menu = MenuTree::new(options::pause_menu(&ctx));
         Self {
-            menu: Menu::new(),
+            menu,
+            pause_menu,
             state: GameState::StartMenu,
-            game: Game::new(),
-            physics: GamePhysics::new(),
+            game,
+            physics,
             animations,
-            player: AudioPlayer::new(),
-            event: None,
-            controls: Controls::default(),
+            player: AudioPlayer::new(settings.audio.clone()),
+            game_events: vec![],
+            controls,
+            gamepads: Gamepads::new(),
+            queue: InputQueue::new(settings.input_lock()),
+            last_update: None,
+            run_time: Duration::ZERO,
+            recorder: None,
+            playback: None,
+            mode,
+            difficulties,
+            highscores: HighScores::load(),
+            name: String::new(),
+            last_rank: None,
+            window_size: (
+                settings.display.width as f64,
+                settings.display.height as f64,
+            ),
+            cursor: None,
+            fonts,
+            rebind_error: None,
+            rebind_return: GameState::StartMenu,
+            saved_display: settings.display.clone(),
+            settings,
+            monitors,
+            languages,
+            locale,
+            display_changed: false,
+        }
+    }
+
+    fn menu_context(&self) -> MenuContext<'_> {
+        MenuContext {
+            settings: &self.settings,
+            controls: &self.controls,
+            monitors: &self.monitors,
+            languages: &self.languages,
+            locale: &self.locale,
         }
     }
 
-    pub fn update(&mut self, events: FlushedMessages<(f64, WindowEvent)>) -> bool {
-        let mut to_quit = false;
-        let action = events
-            .map(|(_, e)| {
-                if let WindowEvent::Close = e {
-                    to_quit = true
+    fn save_settings(&self) {
+        let settings = Settings {
+            display: self.saved_display.clone(),
+            ..self.settings.clone()
+        };
+        if let Err(e) = settings.save() {
+            eprintln!("cannot save settings:\n{}", e);
+        }
+    }
+
+    // Display changes are picked up by `take_display_change`
+    fn apply_settings(&mut self) {
+        self.player.set_volumes(self.settings.audio.clone());
+        self.save_settings();
+    }
+
+    fn save_bindings(&mut self) {
+        self.settings.bindings = self.controls.to_names();
+        options::update_controls_menu(&mut self.menu, &self.controls, &self.locale);
+        options::update_controls_menu(&mut self.pause_menu, &self.controls, &self.locale);
+        self.save_settings();
+    }
+
+    fn rebind(&mut self, action: GameAction, events: &[WindowEvent], pad_inputs: &[PadInput]) {
+        let button = pad_inputs.iter().find_map(|&input| match input {
+            PadInput::Button(button) => Some(button),
+            PadInput::Stick(_) => None,
+        });
+        if let Some(button) = button {
+            match self.controls.rebind_button(action, button) {
+                Ok(()) => {
+                    self.save_bindings();
+                    self.state = self.rebind_return;
                 }
-                e
-            })
-            .find_map(|e| self.controls.convert(e));
+                Err(other) => {
+                    let action = self.locale.get(other.text_key());
+                    let error = self
+                        .locale
+                        .format("rebind.button_used", &[("action", action)]);
+                    self.rebind_error = Some(error);
+                }
+            }
+            return;
+        }
+        let key = events.iter().find_map(|e| match *e {
+            WindowEvent::Key(key, _, Action::Press, _) => Some(key),
+            _ => None,
+        });
+        match key {
+            None => (),
+            Some(Key::Escape) => self.state = self.rebind_return,
+            Some(key) => match controls::key_name(key) {
+                None => {
+                    let error = self.locale.get("rebind.cannot_bind").to_string();
+                    self.rebind_error = Some(error);
+                }
+                Some(name) => match self.controls.rebind(action, key) {
+                    Ok(()) => {
+                        self.save_bindings();
+                        self.state = self.rebind_return;
+                    }
+                    Err(other) => {
+                        let name = name.to_uppercase();
+                        let action = self.locale.get(other.text_key());
+                        let error = self
+                            .locale
+                            .format("rebind.key_used", &[("key", &name), ("action", action)]);
+                        self.rebind_error = Some(error);
+                    }
+                },
+            },
+        }
+    }
+
+    // Rebuilds the menu that is not shown so that both show the same settings
+    fn sync_menus(&mut self) {
+        if let GameState::Paused = self.state {
+            let menu = options::main_menu(&self.menu_context(), self.mode);
+            self.menu = MenuTree::new(menu);
+        } else {
+            let menu = options::pause_menu(&self.menu_context());
+            self.pause_menu = MenuTree::new(menu);
+        }
+    }
+
+    // Rebuilds both menus, e.g. in a new language or with other choices,
+    // staying on the open submenu
+    fn rebuild_menus(&mut self) {
+        let menu = options::main_menu(&self.menu_context(), self.mode);
+        self.menu.replace(menu);
+        let menu = options::pause_menu(&self.menu_context());
+        self.pause_menu.replace(menu);
+    }
+
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        let menu = options::main_menu(&self.menu_context(), mode);
+        self.menu = MenuTree::new(menu);
+        self.return_to_menu();
+    }
+
+    // Uses `display` without saving it, until the display is changed from the
+    // options menu
+    pub fn override_display(&mut self, display: DisplaySettings) {
+        self.window_size = (display.width as f64, display.height as f64);
+        self.settings.display = display;
+        self.rebuild_menus();
+    }
+
+    // The display settings to switch the window to, if they changed since the
+    // last call
+    pub fn take_display_change(&mut self) -> Option<&DisplaySettings> {
+        if std::mem::take(&mut self.display_changed) {
+            Some(&self.settings.display)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    // Starts a run driven by the recorded actions instead of live input
+    pub fn start_playback(&mut self, replay: Replay) {
+        let playback = Playback::new(replay);
+        self.mode = playback.mode();
+        self.game = Game::with_seed(self.difficulties.profile(self.mode), playback.seed());
+        self.physics.reset(playback.seed());
+        self.playback = Some(playback);
+        self.start_run();
+    }
+
+    fn start_run(&mut self) {
+        self.state = GameState::InGame;
+        self.run_time = Duration::ZERO;
+        self.queue.clear();
+        if let (Some(recorder), None) = (&mut self.recorder, &self.playback) {
+            recorder.start(self.game.get_seed(), self.mode);
+        }
+    }
+
+    fn end_run(&mut self, event: GameEvent) {
+        self.state = GameState::GameOver;
+        self.game_events.push(event);
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(e) = recorder.finish() {
+                eprintln!("cannot save replay:\n{}", e);
+            }
+        }
+    }
+
+    fn pause(&mut self) {
+        self.state = GameState::Paused;
+        self.queue.clear();
+        self.animations.pause();
+        self.pause_menu.reset();
+        self.pause_menu.select(0);
+    }
+
+    fn resume(&mut self) {
+        self.state = GameState::InGame;
+        self.animations.resume();
+    }
+
+    fn restart(&mut self) {
+        self.animations.resume();
+        self.playback = None;
+        self.reset_game();
+        self.start_run();
+    }
+
+    fn return_to_menu(&mut self) {
+        self.animations.resume();
+        self.state = GameState::StartMenu;
+        self.menu.reset();
+        self.playback = None;
+        self.last_rank = None;
+        self.reset_game();
+    }
+
+    fn reset_game(&mut self) {
+        self.game = Game::new(self.difficulties.profile(self.mode));
+        self.physics.reset(self.game.get_seed());
+    }
+
+    fn edit_name(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::Char(c) if !c.is_control() && self.name.chars().count() < MAX_NAME_LEN => {
+                self.name.push(c)
+            }
+            WindowEvent::Key(Key::Backspace, _, Action::Press | Action::Repeat, _) => {
+                self.name.pop();
+            }
+            _ => (),
+        }
+    }
+
+    fn submit_high_score(&mut self) {
+        let name = match self.name.trim() {
+            "" => self.locale.get("highscores.default_name").to_string(),
+            name => name.to_string(),
+        };
+        let entry = HighScore::new(name, self.game.get_score(), self.game.get_seed(), self.mode);
+        self.last_rank = self.highscores.insert(entry);
+        if let Err(e) = self.highscores.save() {
+            eprintln!("cannot save high scores:\n{}", e);
+        }
+        self.state = GameState::HighScores;
+    }
+
+    fn ui_space(&self) -> UiSpace {
+        let (width, height) = self.window_size;
+        UiSpace::new(width, height)
+    }
+
+    // Converts a cursor position in window pixels to UI coordinates
+    fn to_ui(&self, x: f64, y: f64) -> (f32, f32) {
+        let (width, height) = self.window_size;
+        let x = (2. * x / width - 1.) as f32;
+        let y = (1. - 2. * y / height) as f32;
+        self.ui_space().from_window(x, y)
+    }
+
+    fn active_menu(&self) -> Option<&MenuTree> {
         match self.state {
-            GameState::StartMenu => {
-                if let Some(ma) = action.and_then(GameAction::into_menu_action) {
-                    match self.menu.update(ma) {
-                        Some(MenuResult::Start) => {
-                            self.state = GameState::InGame;
+            GameState::StartMenu => Some(&self.menu),
+            GameState::Paused => Some(&self.pause_menu),
+            _ => None,
+        }
+    }
+
+    fn active_menu_mut(&mut self) -> Option<&mut MenuTree> {
+        match self.state {
+            GameState::StartMenu => Some(&mut self.menu),
+            GameState::Paused => Some(&mut self.pause_menu),
+            _ => None,
+        }
+    }
+
+    fn hovered_item(&self) -> Option<usize> {
+        let (x, y) = self.cursor?;
+        game_graphics::menu_item_at(
+            self.active_menu()?.current(),
+            &self.fonts,
+            &self.ui_space(),
+            &self.locale,
+            x,
+            y,
+        )
+    }
+
+    // Clicking or tapping: in menus it picks the item under the cursor, in
+    // game the half of the window chops on that side, elsewhere it continues
+    fn click_action(&mut self) -> Option<GameAction> {
+        match self.state {
+            GameState::StartMenu | GameState::Paused => {
+                let i = self.hovered_item()?;
+                self.active_menu_mut()?.select(i);
+                Some(GameAction::Enter)
+            }
+            GameState::InGame => match self.cursor {
+                Some((x, _)) if x < 0. => Some(GameAction::Left),
+                Some(_) => Some(GameAction::Right),
+                None => None,
+            },
+            GameState::GameOver | GameState::HighScores => Some(GameAction::Enter),
+            _ => None,
+        }
+    }
+
+    // Inputs of the frame converted to actions, in the order they happened
+    fn collect_actions(
+        &mut self,
+        events: &[(f64, WindowEvent)],
+        pad_inputs: &[PadInput],
+        now: f64,
+    ) -> Vec<TimedAction> {
+        let mut actions = vec![];
+        for (time, e) in events {
+            let action = match *e {
+                WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => {
+                    self.click_action()
+                }
+                _ => self.controls.convert(e.clone()),
+            };
+            if let Some(action) = action {
+                actions.push(TimedAction {
+                    time: *time,
+                    action,
+                });
+            }
+        }
+        // Gamepads are polled, so their exact press time is unknown
+        let pad_actions = pad_inputs
+            .iter()
+            .filter_map(|&input| self.controls.convert_pad(input))
+            .map(|action| TimedAction { time: now, action });
+        actions.extend(pad_actions);
+        actions.sort_by(|a, b| a.time.total_cmp(&b.time));
+        actions
+    }
+
+    // Returns whether the game should quit
+    fn on_menu_event(&mut self, event: MenuEvent) -> bool {
+        match event {
+            MenuEvent::Pressed(MenuCommand::Start) => self.start_run(),
+            MenuEvent::Pressed(MenuCommand::HighScores) => self.state = GameState::HighScores,
+            MenuEvent::Pressed(MenuCommand::Quit) => return true,
+            MenuEvent::Pressed(MenuCommand::LoadProfile(i)) => {
+                self.controls = Controls::profile(i);
+                self.save_bindings();
+            }
+            MenuEvent::Pressed(MenuCommand::Rebind(action)) => {
+                self.rebind_error = None;
+                self.rebind_return = self.state;
+                self.state = GameState::Rebind(action);
+            }
+            MenuEvent::Pressed(MenuCommand::Resume) => self.resume(),
+            MenuEvent::Pressed(MenuCommand::Restart) => self.restart(),
+            MenuEvent::Pressed(MenuCommand::QuitToMenu) => self.return_to_menu(),
+            // Backing out of the pause menu resumes the run
+            MenuEvent::Closed => {
+                if let GameState::Paused = self.state {
+                    self.resume();
+                }
+            }
+            MenuEvent::Changed(command, _) => {
+                if options::apply(event, &mut self.settings, &self.languages) {
+                    match command {
+                        MenuCommand::Mode => {
+                            self.mode = self.settings.mode;
+                            self.reset_game();
+                        }
+                        // The whole display as shown is kept, overrides included
+                        MenuCommand::Resolution
+                        | MenuCommand::WindowMode
+                        | MenuCommand::Monitor
+                        | MenuCommand::VSync => {
+                            self.saved_display = self.settings.display.clone();
+                            self.display_changed = true;
+                        }
+                        _ => (),
+                    }
+                    self.apply_settings();
+                    match command {
+                        MenuCommand::Language => {
+                            self.locale = Locale::load(&self.settings.language);
+                            self.rebuild_menus();
                         }
-                        Some(MenuResult::Quit) => to_quit = true,
-                        None => (),
+                        // A custom resolution is no longer among the choices
+                        MenuCommand::Resolution => self.rebuild_menus(),
+                        _ => self.sync_menus(),
                     }
                 }
             }
+            MenuEvent::Pressed(_) => (),
+        }
+        false
+    }
+
+    // Advances the run by `dt` then applies the action, returning whether the run ended
+    fn step_game(&mut self, dt: Duration, action: Option<GameAction>) -> bool {
+        self.run_time += dt;
+        // The timer is ticked before the action so that the drain only
+        // depends on the score, not on how time was split into frames
+        if let Some(event) = self.game.tick(dt) {
+            self.end_run(event);
+            return true;
+        }
+        let action = match action {
+            Some(action) => action,
+            None => return false,
+        };
+        if let Some(pa) = action.into_player_action() {
+            if let Some(recorder) = &mut self.recorder {
+                recorder.record(self.run_time, action);
+            }
+            let event = self.game.update(pa);
+            self.animations.update();
+            self.physics.update(&self.game, pa);
+            if let GameEvent::Finished(_) = event {
+                self.end_run(event);
+                return true;
+            }
+            self.game_events.push(event);
+        }
+        false
+    }
+
+    fn update_game(&mut self, frame_start: f64, now: f64) {
+        let secs = |dt: f64| Duration::from_secs_f64(dt.max(0.));
+        if self.playback.is_some() {
+            let mut remaining = secs(now - frame_start);
+            loop {
+                let playback = self.playback.as_mut().unwrap();
+                let (dt, action) = playback.next(self.run_time, remaining);
+                remaining = remaining.saturating_sub(dt);
+                if self.step_game(dt, action) || action.is_none() {
+                    break;
+                }
+            }
+        } else {
+            let mut clock = frame_start;
+            while let Some(input) = self.queue.pop(now) {
+                let time = input.time.max(clock);
+                if input.action.into_player_action().is_some() {
+                    self.queue.lock(time);
+                }
+                let ended = self.step_game(secs(time - clock), Some(input.action));
+                clock = time;
+                if ended {
+                    break;
+                }
+            }
+            if let GameState::InGame = self.state {
+                self.step_game(secs(now - clock), None);
+            }
+        }
+        self.physics.advance(frame_time(frame_start, now));
+    }
+
+    // Handles one action in any state but in game, where actions are queued
+    fn handle_action(&mut self, input: TimedAction) -> bool {
+        let action = input.action;
+        match self.state {
+            GameState::StartMenu | GameState::Paused => {
+                let event = action
+                    .into_menu_action()
+                    .zip(self.active_menu_mut())
+                    .and_then(|(ma, menu)| menu.update(ma));
+                if let Some(event) = event {
+                    return self.on_menu_event(event);
+                }
+            }
             GameState::InGame => {
-                if let Some(pa) = action.and_then(GameAction::into_player_action) {
-                    let event = self.game.update(pa);
-                    self.animations.update();
-                    self.physics.update(&self.game, pa);
-                    self.physics.step();
-                    if let GameEvent::Finished(_) = event {
-                        self.state = GameState::GameOver;
-                    }
-                    self.event = Some(event);
-                } else {
-                    self.physics.step();
-                    self.event = None;
+                if let GameAction::Back = action {
+                    self.pause();
+                } else if self.playback.is_none() {
+                    self.queue.push(input.time, action);
                 }
             }
             GameState::GameOver => {
-                self.physics.step();
-                if let Some(GameAction::Enter) = action {
-                    self.state = GameState::StartMenu;
-                    self.game = Game::new();
-                    self.physics.reset();
+                if let GameAction::Enter = action {
+                    if self.playback.is_none() && self.highscores.qualifies(self.game.get_score()) {
+                        self.name.clear();
+                        self.state = GameState::EnterName;
+                    } else {
+                        self.return_to_menu();
+                    }
                 }
             }
+            GameState::EnterName => {
+                if let GameAction::Enter = action {
+                    self.submit_high_score();
+                }
+            }
+            GameState::HighScores => {
+                if let GameAction::Enter | GameAction::Back = action {
+                    self.return_to_menu();
+                }
+            }
+            GameState::Rebind(_) => (),
+        }
+        false
+    }
+
+    pub fn update(&mut self, glfw: &Glfw, events: FlushedMessages<(f64, WindowEvent)>) -> bool {
+        let now = glfw.get_time();
+        let frame_start = self.last_update.unwrap_or(now);
+        self.last_update = Some(now);
+        self.game_events.clear();
+        let events: Vec<(f64, WindowEvent)> = events.collect();
+        let mut to_quit = events.iter().any(|(_, e)| matches!(e, WindowEvent::Close));
+        for (_, e) in &events {
+            match *e {
+                WindowEvent::Size(width, height) => {
+                    self.window_size = (width as f64, height as f64)
+                }
+                WindowEvent::CursorPos(x, y) => {
+                    self.cursor = Some(self.to_ui(x, y));
+                    if let Some(i) = self.hovered_item() {
+                        self.active_menu_mut().unwrap().select(i);
+                    }
+                }
+                WindowEvent::Focus(false) => {
+                    if let GameState::InGame = self.state {
+                        self.pause();
+                    }
+                }
+                _ => (),
+            }
+        }
+        let pad_inputs = self.gamepads.poll(glfw);
+        if let GameState::Rebind(action) = self.state {
+            let window_events: Vec<_> = events.into_iter().map(|(_, e)| e).collect();
+            self.rebind(action, &window_events, &pad_inputs);
+            return to_quit;
+        }
+        if let GameState::EnterName = self.state {
+            for (_, e) in &events {
+                self.edit_name(e);
+            }
+        }
+        for input in self.collect_actions(&events, &pad_inputs, now) {
+            to_quit |= self.handle_action(input);
+        }
+        match self.state {
+            GameState::InGame => self.update_game(frame_start, now),
+            GameState::GameOver => {
+                self.physics.advance(frame_time(frame_start, now));
+            }
+            _ => (),
         }
         to_quit
     }
 
     pub fn play_bgm(&mut self, resources: &AudioResources) {
-        self.player.play(resources.bgm.clone());
+        if let Err(e) = self.player.play(Sound::Music, resources) {
+            eprintln!("{}", e);
+        }
     }
 
+    // One chop sound per chop, even when several happened in the same frame
     pub fn play_audio(&mut self, resources: &AudioResources) {
-        match self.state {
-            GameState::InGame => {
-                if let Some(GameEvent::Performed(_action)) = self.event {
-                    self.player.play(resources.chop.clone())
+        for event in &self.game_events {
+            if let GameEvent::Performed(_action) = event {
+                if let Err(e) = self.player.play(Sound::Chop, resources) {
+                    eprintln!("{}", e);
                 }
             }
-            _ => (),
         }
     }
 
-    pub fn make_ui(&self, resources: &UIResources) -> Vec<GameObject> {
+    pub fn draw_ui(&self, list: &mut DrawList, resources: &UIResources) {
+        let ui = self.ui_space();
+        let locale = &self.locale;
+        list.set_ui_transform(ui.to_clip());
         match self.state {
-            GameState::StartMenu => game_graphics::make_menu(&self.menu, resources),
-            GameState::InGame => game_graphics::make_ui(&self.game, resources),
-            GameState::GameOver => game_graphics::make_game_over_ui(&self.game, resources),
+            GameState::StartMenu => {
+                game_graphics::draw_menu(list, &self.menu, resources, &ui, locale)
+            }
+            GameState::Paused => {
+                game_graphics::draw_dim_overlay(list, resources, &ui);
+                game_graphics::draw_menu(list, &self.pause_menu, resources, &ui, locale);
+            }
+            GameState::InGame => game_graphics::draw_ui(list, &self.game, resources, &ui, locale),
+            GameState::GameOver => {
+                game_graphics::draw_game_over_ui(list, &self.game, resources, &ui, locale)
+            }
+            GameState::EnterName => {
+                game_graphics::draw_name_entry_ui(list, &self.name, resources, &ui, locale)
+            }
+            GameState::HighScores => game_graphics::draw_high_scores_ui(
+                list,
+                &self.highscores,
+                self.last_rank,
+                resources,
+                &ui,
+                locale,
+            ),
+            GameState::Rebind(action) => game_graphics::draw_rebind_ui(
+                list,
+                action,
+                self.rebind_error.as_deref(),
+                resources,
+                &ui,
+                locale,
+            ),
         }
     }
 
-    fn make_game_scene(&self, resources: &GameResources) -> Vec<GameObject> {
-        let mut scene = self.physics.make_scene(&self.game, resources);
-        scene.push(game_graphics::make_player(
-            &self.game,
-            resources,
-            &self.animations.chop,
-        ));
-        scene
+    fn draw_game_scene(&self, list: &mut DrawList, resources: &GameResources) {
+        self.physics.draw_scene(list, &self.game, resources);
+        game_graphics::draw_player(list, &self.game, resources, &self.animations.chop);
     }
 
-    pub fn make_scene(&self, resources: &GameResources) -> Vec<GameObject> {
+    pub fn draw_scene(&self, list: &mut DrawList, resources: &GameResources) {
         match self.state {
-            GameState::StartMenu => vec![],
-            GameState::InGame => self.make_game_scene(resources),
-            GameState::GameOver => self.make_game_scene(resources),
+            GameState::StartMenu => {}
+            GameState::InGame => self.draw_game_scene(list, resources),
+            GameState::Paused => self.draw_game_scene(list, resources),
+            GameState::GameOver => self.draw_game_scene(list, resources),
+            GameState::EnterName => {}
+            GameState::HighScores => {}
+            GameState::Rebind(_) => {}
         }
     }
 }
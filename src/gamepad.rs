@@ -0,0 +1,135 @@
+use glfw::{Action, GamepadAxis, GamepadButton, Glfw, JoystickId};
+
+use crate::controls::GameAction;
+
+// How far the stick has to be pushed before it counts as a direction
+const DEADZONE: f32 = 0.5;
+
+pub const BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::ButtonA,
+    GamepadButton::ButtonB,
+    GamepadButton::ButtonX,
+    GamepadButton::ButtonY,
+    GamepadButton::ButtonLeftBumper,
+    GamepadButton::ButtonRightBumper,
+    GamepadButton::ButtonBack,
+    GamepadButton::ButtonStart,
+    GamepadButton::ButtonGuide,
+    GamepadButton::ButtonLeftThumb,
+    GamepadButton::ButtonRightThumb,
+    GamepadButton::ButtonDpadUp,
+    GamepadButton::ButtonDpadRight,
+    GamepadButton::ButtonDpadDown,
+    GamepadButton::ButtonDpadLeft,
+];
+
+const JOYSTICKS: [JoystickId; 16] = [
+    JoystickId::Joystick1,
+    JoystickId::Joystick2,
+    JoystickId::Joystick3,
+    JoystickId::Joystick4,
+    JoystickId::Joystick5,
+    JoystickId::Joystick6,
+    JoystickId::Joystick7,
+    JoystickId::Joystick8,
+    JoystickId::Joystick9,
+    JoystickId::Joystick10,
+    JoystickId::Joystick11,
+    JoystickId::Joystick12,
+    JoystickId::Joystick13,
+    JoystickId::Joystick14,
+    JoystickId::Joystick15,
+    JoystickId::Joystick16,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PadInput {
+    Button(GamepadButton),
+    // The left stick is always used for directions, it cannot be rebound
+    Stick(GameAction),
+}
+
+#[derive(Default)]
+struct PadState {
+    held: Vec<GamepadButton>,
+    stick: Option<GameAction>,
+}
+
+fn stick_direction(x: f32, y: f32) -> Option<GameAction> {
+    if x.abs().max(y.abs()) < DEADZONE {
+        None
+    } else if x.abs() > y.abs() {
+        Some(if x < 0. {
+            GameAction::Left
+        } else {
+            GameAction::Right
+        })
+    } else if y < 0. {
+        // GLFW reports pushing the stick up as negative
+        Some(GameAction::Up)
+    } else {
+        Some(GameAction::Down)
+    }
+}
+
+// Polls every joystick that GLFW recognizes as a gamepad
+#[derive(Default)]
+pub struct Gamepads {
+    pads: Vec<(JoystickId, PadState)>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Buttons pressed and stick directions entered since the last poll.
+    // Controllers can be plugged in or out at any time.
+    pub fn poll(&mut self, glfw: &Glfw) -> Vec<PadInput> {
+        let mut inputs = vec![];
+        for &id in &JOYSTICKS {
+            let joystick = glfw.get_joystick(id);
+            let state = if joystick.is_gamepad() {
+                joystick.get_gamepad_state()
+            } else {
+                None
+            };
+            let idx = self.pads.iter().position(|(pad_id, _)| *pad_id == id);
+            match (state, idx) {
+                (Some(state), idx) => {
+                    let idx = idx.unwrap_or_else(|| {
+                        let name = joystick.get_gamepad_name().unwrap_or_default();
+                        eprintln!("gamepad connected: {}", name);
+                        self.pads.push((id, PadState::default()));
+                        self.pads.len() - 1
+                    });
+                    let pad = &mut self.pads[idx].1;
+
+                    let held: Vec<_> = BUTTONS
+                        .iter()
+                        .copied()
+                        .filter(|&b| state.get_button_state(b) == Action::Press)
+                        .collect();
+                    let pressed = held.iter().filter(|b| !pad.held.contains(b));
+                    inputs.extend(pressed.map(|&b| PadInput::Button(b)));
+                    pad.held = held;
+
+                    let stick = stick_direction(
+                        state.get_axis(GamepadAxis::AxisLeftX),
+                        state.get_axis(GamepadAxis::AxisLeftY),
+                    );
+                    if let Some(direction) = stick.filter(|&d| Some(d) != pad.stick) {
+                        inputs.push(PadInput::Stick(direction));
+                    }
+                    pad.stick = stick;
+                }
+                (None, Some(idx)) => {
+                    eprintln!("gamepad disconnected");
+                    self.pads.remove(idx);
+                }
+                (None, None) => (),
+            }
+        }
+        inputs
+    }
+}
@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{game::GameMode, paths};
+
+const FILE_NAME: &str = "highscores.json";
+const CAPACITY: usize = 10;
+pub const MAX_NAME_LEN: usize = 12;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub timestamp: u64,
+    pub seed: u64,
+    pub mode: GameMode,
+}
+
+impl HighScore {
+    pub fn new(name: String, score: u32, seed: u64, mode: GameMode) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        Self {
+            name,
+            score,
+            timestamp,
+            seed,
+            mode,
+        }
+    }
+
+    // Formats the timestamp as a UTC date, e.g. 2021-08-30
+    pub fn date(&self) -> String {
+        // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+        let days = (self.timestamp / 86400) as i64 + 719_468;
+        let era = days / 146_097;
+        let doe = days - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+pub struct HighScores {
+    entries: Vec<HighScore>,
+    path: PathBuf,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        Self::load_from(paths::data_file(FILE_NAME))
+    }
+
+    // A missing or unreadable file gives an empty table
+    pub fn load_from(path: PathBuf) -> Self {
+        let entries = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).unwrap_or_else(|e| {
+                eprintln!("cannot read high scores {}:\n{}", path.display(), e);
+                vec![]
+            }),
+            Err(_) => vec![],
+        };
+        Self { entries, path }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        serde_json::to_writer_pretty(&mut writer, &self.entries)?;
+        writer.flush()
+    }
+
+    pub fn entries(&self) -> &[HighScore] {
+        &self.entries
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0
+            && (self.entries.len() < CAPACITY
+                || self.entries.last().is_none_or(|e| score > e.score))
+    }
+
+    // Returns the rank of the new entry if it made it into the table
+    pub fn insert(&mut self, entry: HighScore) -> Option<usize> {
+        if !self.qualifies(entry.score) {
+            return None;
+        }
+        let rank = self
+            .entries
+            .iter()
+            .position(|e| entry.score > e.score)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(CAPACITY);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty() -> HighScores {
+        HighScores {
+            entries: vec![],
+            path: PathBuf::new(),
+        }
+    }
+
+    fn entry(score: u32) -> HighScore {
+        HighScore::new(format!("p{}", score), score, 0, GameMode::Classic)
+    }
+
+    fn scores(table: &HighScores) -> Vec<u32> {
+        table.entries().iter().map(|e| e.score).collect()
+    }
+
+    #[test]
+    fn insert_keeps_descending_order() {
+        let mut table = empty();
+        assert_eq!(table.insert(entry(5)), Some(0));
+        assert_eq!(table.insert(entry(9)), Some(0));
+        assert_eq!(table.insert(entry(7)), Some(1));
+        assert_eq!(scores(&table), vec![9, 7, 5]);
+    }
+
+    #[test]
+    fn equal_score_ranks_below_existing() {
+        let mut table = empty();
+        table.insert(entry(5));
+        let mut late = entry(5);
+        late.name = "late".to_string();
+        assert_eq!(table.insert(late), Some(1));
+        assert_eq!(table.entries()[1].name, "late");
+    }
+
+    #[test]
+    fn zero_score_does_not_qualify() {
+        let mut table = empty();
+        assert_eq!(table.insert(entry(0)), None);
+        assert!(table.entries().is_empty());
+    }
+
+    #[test]
+    fn full_table_drops_lowest() {
+        let mut table = empty();
+        for score in 1..=CAPACITY as u32 {
+            table.insert(entry(score));
+        }
+        assert!(!table.qualifies(1));
+        assert_eq!(table.insert(entry(1)), None);
+        assert_eq!(table.insert(entry(4)), Some(CAPACITY - 3));
+        assert_eq!(table.entries().len(), CAPACITY);
+        assert_eq!(table.entries().last().unwrap().score, 2);
+    }
+}
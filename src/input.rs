@@ -0,0 +1,124 @@
+use std::{collections::VecDeque, time::Duration};
+
+use crate::controls::GameAction;
+
+// An action with the GLFW time, in seconds, at which it was received
+#[derive(Debug, Clone, Copy)]
+pub struct TimedAction {
+    pub time: f64,
+    pub action: GameAction,
+}
+
+// Actions received during a frame, handed out in the order they happened.
+// After each action the queue can be locked for a short time, e.g. while the
+// chop animation plays, and actions arriving meanwhile wait for the lock to end.
+pub struct InputQueue {
+    actions: VecDeque<TimedAction>,
+    lock: f64,
+    locked_until: f64,
+}
+
+impl InputQueue {
+    // A zero lock lets every action through as soon as it arrives
+    pub fn new(lock: Duration) -> Self {
+        Self {
+            actions: VecDeque::new(),
+            lock: lock.as_secs_f64(),
+            locked_until: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn push(&mut self, time: f64, action: GameAction) {
+        let idx = self.actions.partition_point(|a| a.time <= time);
+        self.actions.insert(idx, TimedAction { time, action });
+    }
+
+    pub fn clear(&mut self) {
+        self.actions.clear();
+        self.locked_until = f64::NEG_INFINITY;
+    }
+
+    // The next action that takes effect no later than `until`, with its time
+    // moved to the end of the lock if it arrived during one
+    pub fn pop(&mut self, until: f64) -> Option<TimedAction> {
+        let next = *self.actions.front()?;
+        let time = next.time.max(self.locked_until);
+        if time > until {
+            return None;
+        }
+        self.actions.pop_front();
+        Some(TimedAction { time, ..next })
+    }
+
+    pub fn lock(&mut self, from: f64) {
+        self.locked_until = from + self.lock;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pop_all(queue: &mut InputQueue, until: f64) -> Vec<(f64, GameAction)> {
+        std::iter::from_fn(|| queue.pop(until))
+            .map(|a| (a.time, a.action))
+            .collect()
+    }
+
+    #[test]
+    fn out_of_order_pushes_pop_in_time_order() {
+        let mut queue = InputQueue::new(Duration::ZERO);
+        queue.push(2., GameAction::Left);
+        queue.push(1., GameAction::Right);
+        queue.push(2., GameAction::Up);
+        queue.push(1.5, GameAction::Down);
+        assert_eq!(
+            pop_all(&mut queue, 10.),
+            vec![
+                (1., GameAction::Right),
+                (1.5, GameAction::Down),
+                (2., GameAction::Left),
+                (2., GameAction::Up),
+            ]
+        );
+    }
+
+    #[test]
+    fn pops_only_up_to_until() {
+        let mut queue = InputQueue::new(Duration::ZERO);
+        queue.push(1., GameAction::Left);
+        queue.push(2., GameAction::Right);
+        queue.push(3., GameAction::Left);
+        assert_eq!(
+            pop_all(&mut queue, 2.),
+            vec![(1., GameAction::Left), (2., GameAction::Right)]
+        );
+        assert_eq!(pop_all(&mut queue, 2.5), vec![]);
+        assert_eq!(pop_all(&mut queue, 3.), vec![(3., GameAction::Left)]);
+    }
+
+    #[test]
+    fn lock_delays_actions() {
+        let mut queue = InputQueue::new(Duration::from_millis(500));
+        queue.push(1., GameAction::Left);
+        queue.push(1.2, GameAction::Right);
+        let first = queue.pop(1.).unwrap();
+        queue.lock(first.time);
+        // The second action waits for the lock to end
+        assert!(queue.pop(1.4).is_none());
+        let second = queue.pop(1.5).unwrap();
+        assert_eq!(second.time, 1.5);
+        assert_eq!(second.action, GameAction::Right);
+    }
+
+    #[test]
+    fn clear_drops_actions_and_lock() {
+        let mut queue = InputQueue::new(Duration::from_secs(1));
+        queue.push(1., GameAction::Left);
+        queue.lock(1.);
+        queue.clear();
+        assert!(queue.pop(10.).is_none());
+        queue.push(1.1, GameAction::Right);
+        assert_eq!(queue.pop(1.1).unwrap().time, 1.1);
+    }
+}
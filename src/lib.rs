@@ -1,13 +1,28 @@
+pub mod assets;
 pub mod controls;
 pub mod audio;
+pub mod difficulty;
+pub mod display;
+pub mod error;
+pub mod fonts;
 pub mod game;
 pub mod game_graphics;
+pub mod gamepad;
 pub mod game_physics;
 pub mod game_state;
 pub mod geometry;
+pub mod highscores;
+pub mod input;
+pub mod locale;
 pub mod menu;
+pub mod replay;
 pub mod object;
+pub mod options;
+pub mod paths;
+pub mod renderer;
 pub mod semantics;
+pub mod settings;
+pub mod simulation;
 pub mod text;
 pub mod transform;
 pub mod animation;
@@ -0,0 +1,197 @@
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::Path};
+
+use crate::{assets, error::Result};
+
+const LANG_DIR: &str = "data/lang";
+// Every key has a string in this language, others may leave some out
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Table {
+    // Name of the language in that language, as listed in the options
+    name: String,
+    // Put between groups of three digits in numbers
+    #[serde(default)]
+    digit_separator: String,
+    strings: HashMap<String, String>,
+}
+
+fn load_table(language: &str) -> Result<Table> {
+    assets::load_json(&Path::new(LANG_DIR).join(format!("{}.json", language)))
+}
+
+// Codes and names of the languages that have a table, sorted by code
+pub fn languages() -> Vec<(String, String)> {
+    let entries = match fs::read_dir(LANG_DIR) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("cannot list languages in {}: {}", LANG_DIR, e);
+            return vec![];
+        }
+    };
+    let mut languages: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "json" {
+                return None;
+            }
+            let code = path.file_stem()?.to_str()?.to_string();
+            let table = load_table(&code).ok()?;
+            Some((code, table.name))
+        })
+        .collect();
+    languages.sort();
+    languages
+}
+
+// The strings of one language, falling back to the default language for keys
+// it does not have, then to the key itself
+pub struct Locale {
+    language: String,
+    table: Table,
+    fallback: Table,
+}
+
+impl Locale {
+    pub fn load(language: &str) -> Self {
+        let fallback = load_table(DEFAULT_LANGUAGE).unwrap_or_else(|e| {
+            eprintln!("{}\nmenus will show string keys", e);
+            Table::default()
+        });
+        let table = if language == DEFAULT_LANGUAGE {
+            fallback.clone()
+        } else {
+            load_table(language).unwrap_or_else(|e| {
+                eprintln!("{}\nusing the default language", e);
+                fallback.clone()
+            })
+        };
+        Self {
+            language: language.to_string(),
+            table,
+            fallback,
+        }
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    fn lookup(&self, key: &str) -> Option<&str> {
+        self.table
+            .strings
+            .get(key)
+            .or_else(|| self.fallback.strings.get(key))
+            .map(String::as_str)
+    }
+
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        self.lookup(key).unwrap_or(key)
+    }
+
+    // The string with every `{name}` replaced by its value
+    pub fn format(&self, key: &str, args: &[(&str, &str)]) -> String {
+        args.iter()
+            .fold(self.get(key).to_string(), |text, (name, value)| {
+                text.replace(&format!("{{{}}}", name), value)
+            })
+    }
+
+    // Uses `key.zero`, `key.one` or `key.other` depending on `n`, with `{n}`
+    // replaced by the number. Zero falls back to other.
+    pub fn plural(&self, key: &str, n: u32) -> String {
+        let form = match n {
+            0 if self.lookup(&format!("{}.zero", key)).is_some() => "zero",
+            1 => "one",
+            _ => "other",
+        };
+        let number = self.number(n);
+        self.format(&format!("{}.{}", key, form), &[("n", &number)])
+    }
+
+    // The number with its digits grouped by three
+    pub fn number(&self, n: u32) -> String {
+        let separator = match self.table.digit_separator.as_str() {
+            "" => &self.fallback.digit_separator,
+            separator => separator,
+        };
+        let digits = n.to_string();
+        let mut text = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                text.push_str(separator);
+            }
+            text.push(c);
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(digit_separator: &str, strings: &[(&str, &str)]) -> Table {
+        Table {
+            name: String::new(),
+            digit_separator: digit_separator.to_string(),
+            strings: strings
+                .iter()
+                .map(|&(key, text)| (key.to_string(), text.to_string()))
+                .collect(),
+        }
+    }
+
+    fn make_locale(table: Table, fallback: Table) -> Locale {
+        Locale {
+            language: "test".to_string(),
+            table,
+            fallback,
+        }
+    }
+
+    const LOGS: &[(&str, &str)] = &[("logs.one", "{n} log"), ("logs.other", "{n} logs")];
+
+    #[test]
+    fn number_groups_digits_by_three() {
+        let locale = make_locale(table(",", &[]), Table::default());
+        assert_eq!(locale.number(0), "0");
+        assert_eq!(locale.number(999), "999");
+        assert_eq!(locale.number(1000), "1,000");
+        assert_eq!(locale.number(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn number_separator_falls_back() {
+        let locale = make_locale(table("", &[]), table("\u{a0}", &[]));
+        assert_eq!(locale.number(12345), "12\u{a0}345");
+        let locale = make_locale(Table::default(), Table::default());
+        assert_eq!(locale.number(12345), "12345");
+    }
+
+    #[test]
+    fn plural_picks_the_form() {
+        let locale = make_locale(table(",", LOGS), Table::default());
+        assert_eq!(locale.plural("logs", 1), "1 log");
+        assert_eq!(locale.plural("logs", 2), "2 logs");
+        assert_eq!(locale.plural("logs", 1200), "1,200 logs");
+        // Without a zero form, zero is plural
+        assert_eq!(locale.plural("logs", 0), "0 logs");
+    }
+
+    #[test]
+    fn plural_uses_zero_form_from_either_table() {
+        let zero = [("logs.zero", "no logs")];
+        let locale = make_locale(table("", LOGS), table("", &zero));
+        assert_eq!(locale.plural("logs", 0), "no logs");
+        assert_eq!(locale.plural("logs", 1), "1 log");
+    }
+
+    #[test]
+    fn missing_keys_fall_back() {
+        let locale = make_locale(table("", &[]), table("", LOGS));
+        assert_eq!(locale.get("logs.one"), "{n} log");
+        assert_eq!(locale.get("missing"), "missing");
+    }
+}
@@ -1,45 +1,138 @@
 use glfw::Context as _;
 use lumber::{
     animation::GameAnimations,
+    assets::{AssetManifest, MANIFEST_PATH},
     audio::AudioResources,
+    display,
+    error::Result,
+    fonts::Fonts,
+    game::GameMode,
     game_graphics::{self, GameResources, UIResources},
     game_state::GameRunner,
     object,
-    semantics::{Semantics, ShaderInterface},
-};
-use luminance_front::{
-    blending::{Blending, Equation, Factor},
-    context::GraphicsContext,
-    depth_test::DepthWrite,
-    pipeline::PipelineState,
-    render_state::RenderState,
+    renderer::{DrawList, Renderer},
+    replay::{Recorder, Replay},
+    settings::{DisplaySettings, Settings, WindowMode},
 };
 use luminance_glfw::GlfwSurface;
 use luminance_windowing::{WindowDim, WindowOpt};
-use nalgebra::{Matrix4, Point3, Vector3};
-use std::process::exit;
-
-const VS_STR: &str = include_str!("vs.glsl");
-const FS_STR: &str = include_str!("fs.glsl");
-
-const UI_VS_STR: &str = include_str!("ui_vs.glsl");
-const UI_FS_STR: &str = include_str!("ui_fs.glsl");
+use rapier3d::na::{Matrix4, Point3, Vector3};
+use std::{
+    path::{Path, PathBuf},
+    process::exit,
+    rc::Rc,
+};
 
 const FOVY: f32 = std::f32::consts::FRAC_PI_2;
 const Z_NEAR: f32 = 0.1;
 const Z_FAR: f32 = 10.;
 
+#[derive(Default)]
+struct Args {
+    record: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    mode: Option<GameMode>,
+    window_mode: Option<WindowMode>,
+    monitor: Option<usize>,
+    size: Option<(u32, u32)>,
+    vsync: Option<bool>,
+}
+
+impl Args {
+    // Display options given on the command line take precedence over the
+    // settings file, for this run only
+    fn override_display(&self, display: &mut DisplaySettings) {
+        if let Some(mode) = self.window_mode {
+            display.window_mode = mode;
+        }
+        if let Some(monitor) = self.monitor {
+            display.monitor = monitor;
+        }
+        if let Some((width, height)) = self.size {
+            display.width = width;
+            display.height = height;
+        }
+        if let Some(vsync) = self.vsync {
+            display.vsync = vsync;
+        }
+    }
+}
+
+fn parse_size(s: &str) -> Option<(u32, u32)> {
+    let (width, height) = s.split_once('x')?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Some((width, height)),
+        _ => None,
+    }
+}
+
+fn parse_args() -> Args {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--record" => args.record = iter.next().map(PathBuf::from),
+            "--replay" => args.replay = iter.next().map(PathBuf::from),
+            "--mode" => match iter.next().map(|m| m.parse()) {
+                Some(Ok(mode)) => args.mode = Some(mode),
+                _ => {
+                    eprintln!("expected a game mode: classic, casual or expert");
+                    exit(1);
+                }
+            },
+            "--windowed" => args.window_mode = Some(WindowMode::Windowed),
+            "--fullscreen" => args.window_mode = Some(WindowMode::Fullscreen),
+            "--borderless" => args.window_mode = Some(WindowMode::Borderless),
+            "--monitor" => match iter.next().map(|m| m.parse::<usize>()) {
+                // Numbered from 1 on the command line
+                Some(Ok(monitor)) if monitor > 0 => args.monitor = Some(monitor - 1),
+                _ => {
+                    eprintln!("expected a monitor number, starting at 1");
+                    exit(1);
+                }
+            },
+            "--size" => match iter.next().and_then(|s| parse_size(&s)) {
+                Some(size) => args.size = Some(size),
+                None => {
+                    eprintln!("expected a size such as 1280x720");
+                    exit(1);
+                }
+            },
+            "--vsync" => args.vsync = Some(true),
+            "--no-vsync" => args.vsync = Some(false),
+            _ => {
+                eprintln!("unknown argument: {}", arg);
+                exit(1);
+            }
+        }
+    }
+    args
+}
+
+fn or_exit<T>(result: Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1);
+    })
+}
+
 fn main() {
+    let args = parse_args();
+    let settings = Settings::load();
+    let mut display_settings = settings.display.clone();
+    args.override_display(&mut display_settings);
+    // The window starts windowed and is then moved to the chosen monitor and mode
     let dim = WindowDim::Windowed {
-        width: 960,
-        height: 540,
+        width: display_settings.width,
+        height: display_settings.height,
     };
     let surface = GlfwSurface::new_gl33("Lumberman™", WindowOpt::default().set_dim(dim));
 
     match surface {
-        Ok(surface) => {
+        Ok(mut surface) => {
             eprintln!("graphics surface created");
-            main_loop(surface);
+            display::apply(&mut surface.context.window, &display_settings);
+            main_loop(surface, args, settings, display_settings);
         }
 
         Err(e) => {
@@ -49,40 +142,60 @@ fn main() {
     }
 }
 
-fn main_loop(surface: GlfwSurface) {
+fn make_projection([width, height]: [u32; 2]) -> Matrix4<f32> {
+    let aspect = width as f32 / height.max(1) as f32;
+    Matrix4::new_perspective(aspect, FOVY, Z_NEAR, Z_FAR)
+}
+
+fn main_loop(
+    surface: GlfwSurface,
+    args: Args,
+    settings: Settings,
+    display_settings: DisplaySettings,
+) {
     let mut ctxt = surface.context;
     let events = surface.events_rx;
-    let back_buffer = ctxt.back_buffer().expect("back buffer");
-
-    let mut program = ctxt
-        .new_shader_program::<Semantics, (), ShaderInterface>()
-        .from_strings(VS_STR, None, None, FS_STR)
-        .unwrap()
-        .ignore_warnings();
-
-    let mut ui_program = ctxt
-        .new_shader_program::<Semantics, (), ShaderInterface>()
-        .from_strings(UI_VS_STR, None, None, UI_FS_STR)
-        .unwrap()
-        .ignore_warnings();
-
-    let render_st = &RenderState::default().set_blending(Blending {
-        equation: Equation::Additive,
-        src: Factor::SrcAlpha,
-        dst: Factor::SrcAlphaComplement,
-    });
+    let mut back_buffer = ctxt.back_buffer().expect("back buffer");
+
+    let mut renderer = or_exit(Renderer::new(&mut ctxt));
+    let mut draw_list = DrawList::new();
+
+    let manifest = or_exit(AssetManifest::load(Path::new(MANIFEST_PATH)));
 
     let mut rm = object::ResourceManager::new();
-    let game_resources = GameResources::new(&mut rm, &mut ctxt);
-    let ui_resources = UIResources::new(&mut rm, &mut ctxt);
-    let game_animations = GameAnimations::new(&mut rm, &mut ctxt);
-    let audio_resources = AudioResources::new();
-    let mut runner = GameRunner::new(game_animations);
+    let game_resources = or_exit(GameResources::new(&mut rm, &mut ctxt, &manifest));
+    let fonts = Rc::new(or_exit(Fonts::load(&manifest.fonts)));
+    let mut ui_resources = or_exit(UIResources::new(&mut rm, &mut ctxt, fonts.clone()));
+    let game_animations = or_exit(GameAnimations::new(
+        &mut rm,
+        &mut ctxt,
+        &manifest.animations,
+    ));
+    let audio_resources = AudioResources::new(&manifest);
+    let monitors = display::monitor_names(&mut ctxt.window.glfw);
+    let mut runner = GameRunner::new(game_animations, settings, monitors, fonts);
+    runner.override_display(display_settings);
+    if let Some(mode) = args.mode {
+        runner.set_mode(mode);
+    }
+    if let Some(path) = args.record {
+        runner.set_recorder(Recorder::new(path));
+    }
+    if let Some(path) = args.replay {
+        match Replay::load(&path) {
+            Ok(replay) => runner.start_playback(replay),
+            Err(e) => {
+                eprintln!("cannot load replay {}:\n{}", path.display(), e);
+                exit(1);
+            }
+        }
+    }
 
-    let background_object = game_graphics::make_background(&mut rm, &mut ctxt);
+    let background = or_exit(game_graphics::make_background(
+        &mut rm, &mut ctxt, &manifest,
+    ));
 
-    let [width, height] = back_buffer.size();
-    let projection = Matrix4::new_perspective(width as f32 / height as f32, FOVY, Z_NEAR, Z_FAR);
+    let mut projection = make_projection(back_buffer.size());
 
     let view = Matrix4::look_at_rh(
         &Point3::new(0., 1.2, 2.5),
@@ -95,63 +208,38 @@ fn main_loop(surface: GlfwSurface) {
     'app: loop {
         ctxt.window.glfw.poll_events();
 
-        if runner.update(glfw::flush_messages(&events)) {
+        // Resizes and fullscreen toggles change the framebuffer size, it is
+        // zero while the window is minimized
+        let (width, height) = ctxt.window.get_framebuffer_size();
+        let size = [width as u32, height as u32];
+        if width > 0 && height > 0 && size != back_buffer.size() {
+            back_buffer = ctxt.back_buffer().expect("back buffer");
+            projection = make_projection(back_buffer.size());
+        }
+
+        if runner.update(&ctxt.window.glfw, glfw::flush_messages(&events)) {
             break 'app;
         }
+        if let Some(display) = runner.take_display_change() {
+            display::apply(&mut ctxt.window, display);
+        }
         runner.play_audio(&audio_resources);
 
-        let ui_objects = runner.make_ui(&ui_resources);
-        let game_objects = runner.make_scene(&game_resources);
-
-        let render = ctxt
-            .new_pipeline_gate()
-            .pipeline(
-                &back_buffer,
-                &PipelineState::default(),
-                |pipeline, mut shd_gate| {
-                    shd_gate.shade(&mut ui_program, |mut iface, uni, mut rdr_gate| {
-                        rdr_gate.render(
-                            &RenderState::default().set_depth_write(DepthWrite::Off),
-                            |mut tess_gate| {
-                                let bound_tex = pipeline
-                                    .bind_texture(rm.get_texture(&background_object.texture))?;
-                                iface.set(&uni.tex, bound_tex.binding());
-                                iface.set(&uni.model, background_object.transform.into());
-                                tess_gate.render(rm.get_tess(&background_object.tess))
-                            },
-                        )
-                    })?;
-                    shd_gate.shade(&mut program, |mut iface, uni, mut rdr_gate| {
-                        iface.set(&uni.projection, projection.into());
-                        iface.set(&uni.view, view.into());
-                        rdr_gate.render(&render_st, |mut tess_gate| {
-                            game_objects.iter().try_for_each(|gm| {
-                                gm.model.iter().try_for_each(|o| {
-                                    let bound_tex =
-                                        pipeline.bind_texture(rm.get_texture(&o.texture))?;
-                                    iface.set(&uni.tex, bound_tex.binding());
-                                    iface.set(&uni.model, (gm.transform * o.transform).into());
-                                    tess_gate.render(rm.get_tess(&o.tess))
-                                })
-                            })
-                        })
-                    })?;
-                    shd_gate.shade(&mut ui_program, |mut iface, uni, mut rdr_gate| {
-                        rdr_gate.render(&render_st, |mut tess_gate| {
-                            ui_objects.iter().try_for_each(|ui| {
-                                ui.model.iter().try_for_each(|o| {
-                                    let bound_tex =
-                                        pipeline.bind_texture(rm.get_texture(&o.texture))?;
-                                    iface.set(&uni.tex, bound_tex.binding());
-                                    iface.set(&uni.model, (ui.transform * o.transform).into());
-                                    tess_gate.render(rm.get_tess(&o.tess))
-                                })
-                            })
-                        })
-                    })
-                },
-            )
-            .assume();
+        draw_list.clear();
+        draw_list.background(&background);
+        runner.draw_scene(&mut draw_list, &game_resources);
+        runner.draw_ui(&mut draw_list, &ui_resources);
+        or_exit(ui_resources.update(&mut rm));
+        or_exit(renderer.prepare(&mut ctxt, &mut draw_list));
+
+        let render = renderer.render(
+            &mut ctxt,
+            &back_buffer,
+            &mut rm,
+            &draw_list,
+            &projection,
+            &view,
+        );
 
         // swap buffer chains
         if render.is_ok() {
@@ -1,40 +1,460 @@
+use crate::{audio::Channel, controls::GameAction, locale::Locale};
+
 pub enum MenuAction {
     Up,
     Down,
+    Left,
+    Right,
     Select,
+    Back,
 }
 
-#[derive(Clone, Copy)]
-pub enum MenuResult {
+// What a menu item stands for, interpreted by whoever owns the menu
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuCommand {
     Start,
+    HighScores,
     Quit,
+    Resume,
+    Restart,
+    QuitToMenu,
+    Mode,
+    Resolution,
+    WindowMode,
+    Monitor,
+    VSync,
+    Language,
+    Volume(Channel),
+    Mute(Channel),
+    LoadProfile(usize),
+    Rebind(GameAction),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuValue {
+    Bool(bool),
+    Number(f32),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuEvent {
+    Pressed(MenuCommand),
+    Changed(MenuCommand, MenuValue),
+    // Back was used on the root menu
+    Closed,
+}
+
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+}
+
+pub enum ItemKind {
+    Button(MenuCommand),
+    Submenu(Menu),
+    Toggle(MenuCommand, bool),
+    Slider(MenuCommand, Slider),
+    Choice(MenuCommand, Vec<String>, usize),
+    Back,
+}
+
+pub struct MenuItem {
+    pub label: String,
+    pub kind: ItemKind,
+    // Extra text shown next to a button, kept up to date by the menu owner
+    pub detail: Option<String>,
+}
+
+impl MenuItem {
+    pub fn button(label: &str, command: MenuCommand) -> Self {
+        Self::new(label, ItemKind::Button(command))
+    }
+
+    pub fn submenu(menu: Menu) -> Self {
+        Self::new(&menu.title.clone(), ItemKind::Submenu(menu))
+    }
+
+    pub fn toggle(label: &str, command: MenuCommand, value: bool) -> Self {
+        Self::new(label, ItemKind::Toggle(command, value))
+    }
+
+    pub fn slider(label: &str, command: MenuCommand, slider: Slider) -> Self {
+        Self::new(label, ItemKind::Slider(command, slider))
+    }
+
+    pub fn choice(label: &str, command: MenuCommand, choices: Vec<String>, idx: usize) -> Self {
+        Self::new(label, ItemKind::Choice(command, choices, idx))
+    }
+
+    pub fn back(label: &str) -> Self {
+        Self::new(label, ItemKind::Back)
+    }
+
+    fn new(label: &str, kind: ItemKind) -> Self {
+        Self {
+            label: label.to_string(),
+            kind,
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    fn command(&self) -> Option<MenuCommand> {
+        match &self.kind {
+            ItemKind::Button(command)
+            | ItemKind::Toggle(command, _)
+            | ItemKind::Slider(command, _)
+            | ItemKind::Choice(command, ..) => Some(*command),
+            ItemKind::Submenu(_) | ItemKind::Back => None,
+        }
+    }
+
+    // Current value shown next to the label, if the item has one
+    pub fn value_text(&self, locale: &Locale) -> Option<String> {
+        match &self.kind {
+            ItemKind::Toggle(_, true) => Some(locale.get("menu.on").to_string()),
+            ItemKind::Toggle(_, false) => Some(locale.get("menu.off").to_string()),
+            ItemKind::Slider(_, s) => {
+                let fraction = (s.value - s.min) / (s.max - s.min);
+                Some(format!("{}%", (fraction * 100.).round()))
+            }
+            ItemKind::Choice(_, choices, idx) => Some(choices[*idx].clone()),
+            _ => self.detail.clone(),
+        }
+    }
+
+    // Moves the value of the item by `step` notches
+    fn adjust(&mut self, step: isize) -> Option<MenuEvent> {
+        match &mut self.kind {
+            ItemKind::Toggle(command, value) => {
+                *value = !*value;
+                Some(MenuEvent::Changed(*command, MenuValue::Bool(*value)))
+            }
+            ItemKind::Slider(command, s) => {
+                s.value = (s.value + step as f32 * s.step).max(s.min).min(s.max);
+                Some(MenuEvent::Changed(*command, MenuValue::Number(s.value)))
+            }
+            ItemKind::Choice(command, choices, idx) => {
+                let len = choices.len() as isize;
+                *idx = (*idx as isize + step).rem_euclid(len) as usize;
+                Some(MenuEvent::Changed(*command, MenuValue::Index(*idx)))
+            }
+            _ => None,
+        }
+    }
 }
 
+const TITLE_Y: f32 = 0.8;
+const TOP_Y: f32 = 0.5;
+const BOTTOM_Y: f32 = -0.8;
+const MAX_SPACING: f32 = 0.35;
+
 pub struct Menu {
-    pub options: Vec<MenuResult>,
+    pub title: String,
+    pub items: Vec<MenuItem>,
+    // Small print shown at the bottom of the screen
+    pub note: Option<String>,
     pub selected_idx: usize,
 }
 
 impl Menu {
-    pub fn new() -> Self {
+    pub fn new(title: &str, items: Vec<MenuItem>) -> Self {
         Self {
-            options: vec![MenuResult::Start, MenuResult::Quit],
+            title: title.to_string(),
+            items,
+            note: None,
             selected_idx: 0,
         }
     }
 
-    pub fn update(&mut self, action: MenuAction) -> Option<MenuResult> {
-        let mut result = None;
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.note = Some(note.to_string());
+        self
+    }
+
+    pub fn title_y() -> f32 {
+        TITLE_Y
+    }
+
+    // Vertical distance between items, tighter for long menus so they fit on screen
+    pub fn spacing(&self) -> f32 {
+        let gaps = self.items.len().max(2) - 1;
+        ((TOP_Y - BOTTOM_Y) / gaps as f32).min(MAX_SPACING)
+    }
+
+    // Items are laid out top to bottom in UI coordinates, where the screen spans -1 to 1
+    pub fn item_y(&self, i: usize) -> f32 {
+        TOP_Y - self.spacing() * i as f32
+    }
+
+    // Finds the item for a command, looking through submenus
+    pub fn item_mut(&mut self, command: MenuCommand) -> Option<&mut MenuItem> {
+        self.items.iter_mut().find_map(|item| {
+            if item.command() == Some(command) {
+                return Some(item);
+            }
+            match &mut item.kind {
+                ItemKind::Submenu(sub) => sub.item_mut(command),
+                _ => None,
+            }
+        })
+    }
+
+    fn update(&mut self, action: MenuAction) -> Option<MenuEvent> {
+        let len = self.items.len();
         match action {
-            MenuAction::Up => self.selected_idx += self.options.len() + 1,
-            MenuAction::Down => self.selected_idx += 1,
-            MenuAction::Select => result = Some(self.selected()),
+            MenuAction::Up => self.selected_idx = (self.selected_idx + len - 1) % len,
+            MenuAction::Down => self.selected_idx = (self.selected_idx + 1) % len,
+            MenuAction::Left => return self.items[self.selected_idx].adjust(-1),
+            MenuAction::Right => return self.items[self.selected_idx].adjust(1),
+            MenuAction::Select => match &self.items[self.selected_idx].kind {
+                ItemKind::Button(command) => return Some(MenuEvent::Pressed(*command)),
+                ItemKind::Submenu(_) | ItemKind::Back => (),
+                _ => return self.items[self.selected_idx].adjust(1),
+            },
+            MenuAction::Back => (),
         }
-        self.selected_idx = self.selected_idx % 2;
-        result
+        None
     }
+}
+
+// A root menu with the path of submenus the player has opened
+pub struct MenuTree {
+    root: Menu,
+    path: Vec<usize>,
+}
+
+impl MenuTree {
+    pub fn new(root: Menu) -> Self {
+        Self { root, path: vec![] }
+    }
+
+    pub fn current(&self) -> &Menu {
+        self.path
+            .iter()
+            .fold(&self.root, |menu, &i| match &menu.items[i].kind {
+                ItemKind::Submenu(sub) => sub,
+                _ => unreachable!("menu path goes through a non-submenu item"),
+            })
+    }
+
+    fn current_mut(&mut self) -> &mut Menu {
+        let mut menu = &mut self.root;
+        for &i in &self.path {
+            menu = match &mut menu.items[i].kind {
+                ItemKind::Submenu(sub) => sub,
+                _ => unreachable!("menu path goes through a non-submenu item"),
+            };
+        }
+        menu
+    }
+
+    pub fn item_mut(&mut self, command: MenuCommand) -> Option<&mut MenuItem> {
+        self.root.item_mut(command)
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.path.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.path.clear();
+    }
+
+    fn go_back(&mut self) -> Option<MenuEvent> {
+        match self.path.pop() {
+            Some(_) => None,
+            None => Some(MenuEvent::Closed),
+        }
+    }
+
+    pub fn update(&mut self, action: MenuAction) -> Option<MenuEvent> {
+        let menu = self.current_mut();
+        let selected = menu.selected_idx;
+        let kind = &menu.items[selected].kind;
+        let (opens, closes) = (
+            matches!(kind, ItemKind::Submenu(_)),
+            matches!(kind, ItemKind::Back),
+        );
+        match action {
+            MenuAction::Select if opens => {
+                self.path.push(selected);
+                self.current_mut().selected_idx = 0;
+                None
+            }
+            MenuAction::Select if closes => self.go_back(),
+            MenuAction::Back => self.go_back(),
+            _ => self.current_mut().update(action),
+        }
+    }
+
+    // Swaps in a menu with the same layout, e.g. in another language, keeping
+    // the open submenus and selected items
+    pub fn replace(&mut self, mut root: Menu) {
+        let mut old = &self.root;
+        let mut new = &mut root;
+        new.selected_idx = old.selected_idx;
+        for &i in &self.path {
+            match (&old.items[i].kind, &mut new.items[i].kind) {
+                (ItemKind::Submenu(old_sub), ItemKind::Submenu(new_sub)) => {
+                    old = old_sub;
+                    new = new_sub;
+                }
+                _ => unreachable!("menu path goes through a non-submenu item"),
+            }
+            new.selected_idx = old.selected_idx;
+        }
+        self.root = root;
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        self.current_mut().selected_idx = idx;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> Menu {
+        let volume = Slider {
+            value: 0.5,
+            min: 0.,
+            max: 1.,
+            step: 0.25,
+        };
+        let modes = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        Menu::new(
+            "Options",
+            vec![
+                MenuItem::slider("Volume", MenuCommand::Volume(Channel::Master), volume),
+                MenuItem::choice("Mode", MenuCommand::Mode, modes, 0),
+                MenuItem::back("Back"),
+            ],
+        )
+    }
+
+    fn down(tree: &mut MenuTree, times: usize) {
+        for _ in 0..times {
+            tree.update(MenuAction::Down);
+        }
+    }
+
+    fn tree() -> MenuTree {
+        MenuTree::new(Menu::new(
+            "Main",
+            vec![
+                MenuItem::button("Start", MenuCommand::Start),
+                MenuItem::submenu(options()),
+                MenuItem::button("Quit", MenuCommand::Quit),
+            ],
+        ))
+    }
+
+    #[test]
+    fn up_and_down_wrap_around() {
+        let mut tree = tree();
+        assert_eq!(tree.update(MenuAction::Up), None);
+        assert_eq!(tree.current().selected_idx, 2);
+        tree.update(MenuAction::Down);
+        assert_eq!(tree.current().selected_idx, 0);
+        tree.update(MenuAction::Down);
+        assert_eq!(tree.current().selected_idx, 1);
+    }
+
+    #[test]
+    fn select_presses_buttons() {
+        let mut tree = tree();
+        assert_eq!(
+            tree.update(MenuAction::Select),
+            Some(MenuEvent::Pressed(MenuCommand::Start))
+        );
+    }
+
+    #[test]
+    fn submenu_opens_and_goes_back() {
+        let mut tree = tree();
+        down(&mut tree, 1);
+        assert_eq!(tree.update(MenuAction::Select), None);
+        assert!(!tree.is_root());
+        assert_eq!(tree.current().title, "Options");
+        assert_eq!(tree.current().selected_idx, 0);
+
+        // Both the back item and the back action return to the parent
+        down(&mut tree, 2);
+        assert_eq!(tree.update(MenuAction::Select), None);
+        assert!(tree.is_root());
+        assert_eq!(tree.current().selected_idx, 1);
+        tree.update(MenuAction::Select);
+        assert_eq!(tree.update(MenuAction::Back), None);
+        assert!(tree.is_root());
+    }
+
+    #[test]
+    fn back_on_root_closes() {
+        let mut tree = tree();
+        assert_eq!(tree.update(MenuAction::Back), Some(MenuEvent::Closed));
+        assert!(tree.is_root());
+    }
+
+    #[test]
+    fn slider_is_clamped() {
+        let mut tree = tree();
+        down(&mut tree, 1);
+        tree.update(MenuAction::Select);
+        let volume = MenuCommand::Volume(Channel::Master);
+        let changed = |v| Some(MenuEvent::Changed(volume, MenuValue::Number(v)));
+        assert_eq!(tree.update(MenuAction::Right), changed(0.75));
+        assert_eq!(tree.update(MenuAction::Right), changed(1.));
+        assert_eq!(tree.update(MenuAction::Right), changed(1.));
+        for _ in 0..5 {
+            tree.update(MenuAction::Left);
+        }
+        assert_eq!(tree.update(MenuAction::Left), changed(0.));
+    }
+
+    #[test]
+    fn choice_wraps_around() {
+        let mut tree = tree();
+        down(&mut tree, 1);
+        tree.update(MenuAction::Select);
+        down(&mut tree, 1);
+        let changed = |i| Some(MenuEvent::Changed(MenuCommand::Mode, MenuValue::Index(i)));
+        assert_eq!(tree.update(MenuAction::Left), changed(2));
+        assert_eq!(tree.update(MenuAction::Right), changed(0));
+        assert_eq!(tree.update(MenuAction::Select), changed(1));
+    }
+
+    #[test]
+    fn replace_keeps_path_and_selection() {
+        let mut tree = tree();
+        down(&mut tree, 1);
+        tree.update(MenuAction::Select);
+        down(&mut tree, 1);
+
+        let mut options = options();
+        options.title = "Alternativ".to_string();
+        let root = Menu::new(
+            "Huvudmeny",
+            vec![
+                MenuItem::button("Starta", MenuCommand::Start),
+                MenuItem::submenu(options),
+                MenuItem::button("Avsluta", MenuCommand::Quit),
+            ],
+        );
+        tree.replace(root);
 
-    pub fn selected(&self) -> MenuResult {
-        self.options[self.selected_idx]
+        assert_eq!(tree.current().title, "Alternativ");
+        assert_eq!(tree.current().selected_idx, 1);
+        tree.update(MenuAction::Back);
+        assert_eq!(tree.current().title, "Huvudmeny");
+        assert_eq!(tree.current().selected_idx, 1);
     }
 }
@@ -1,5 +1,6 @@
-use crate::semantics::Vertex;
+use crate::{error::Result, semantics::Vertex};
 use image::RgbaImage;
+use luminance::{tess::TessError, texture::TextureError};
 
 use luminance_front::{
     context::GraphicsContext,
@@ -20,7 +21,7 @@ pub struct Mesh {
 }
 
 impl Mesh {
-    pub fn make_tess<C>(self, ctxt: &mut C) -> DefaultTess
+    pub fn make_tess<C>(self, ctxt: &mut C) -> std::result::Result<DefaultTess, TessError>
     where
         C: GraphicsContext<Backend = Backend>,
     {
@@ -29,22 +30,26 @@ impl Mesh {
             .set_vertices(self.vertices)
             .set_indices(self.indices)
             .build()
-            .expect("Building tess")
     }
 }
 
 pub type RgbaTexture = Texture<Dim2, NormRGBA8UI>;
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TessResource {
     idx: u32,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct TextureResource {
     idx: u32,
 }
 
+// RGBA, multiplied with the texture of UI quads
+pub type Color = [f32; 4];
+
+pub const WHITE: Color = [1., 1., 1., 1.];
+
 #[derive(Clone)]
 pub struct Object {
     pub tess: TessResource,
@@ -57,19 +62,17 @@ pub type Model = Vec<Object>;
 fn make_texture(
     context: &mut impl GraphicsContext<Backend = Backend>,
     img: &image::RgbaImage,
-) -> RgbaTexture {
+) -> std::result::Result<RgbaTexture, TextureError> {
     let (width, height) = img.dimensions();
     let texels = img.as_raw();
 
-    context
-        .new_texture_raw(
-            [width, height],
-            0,
-            Sampler::default(),
-            GenMipmaps::No,
-            texels,
-        )
-        .unwrap()
+    context.new_texture_raw(
+        [width, height],
+        0,
+        Sampler::default(),
+        GenMipmaps::No,
+        texels,
+    )
 }
 
 pub struct ResourceManager {
@@ -119,16 +122,16 @@ impl ResourceManager {
         &mut self,
         ctxt: &mut impl GraphicsContext<Backend = Backend>,
         mesh: Mesh,
-    ) -> TessResource {
-        self.add_tess(mesh.make_tess(ctxt))
+    ) -> Result<TessResource> {
+        Ok(self.add_tess(mesh.make_tess(ctxt)?))
     }
 
     pub fn make_texture(
         &mut self,
         ctxt: &mut impl GraphicsContext<Backend = Backend>,
         img: &RgbaImage,
-    ) -> TextureResource {
-        self.add_texture(make_texture(ctxt, img))
+    ) -> Result<TextureResource> {
+        Ok(self.add_texture(make_texture(ctxt, img)?))
     }
 
     pub fn update_tess(
@@ -136,8 +139,9 @@ impl ResourceManager {
         resource: TessResource,
         ctxt: &mut impl GraphicsContext<Backend = Backend>,
         mesh: Mesh,
-    ) {
-        self.tesses.insert(resource.idx, mesh.make_tess(ctxt));
+    ) -> Result<()> {
+        self.tesses.insert(resource.idx, mesh.make_tess(ctxt)?);
+        Ok(())
     }
 
     pub fn update_texture(
@@ -145,7 +149,21 @@ impl ResourceManager {
         resource: TextureResource,
         ctxt: &mut impl GraphicsContext<Backend = Backend>,
         img: &RgbaImage,
-    ) {
-        self.textures.insert(resource.idx, make_texture(ctxt, img));
+    ) -> Result<()> {
+        self.textures.insert(resource.idx, make_texture(ctxt, img)?);
+        Ok(())
+    }
+
+    // Replaces the pixels of the texture from `offset` with those of `img`
+    pub fn update_texture_part(
+        &mut self,
+        resource: TextureResource,
+        offset: [u32; 2],
+        img: &RgbaImage,
+    ) -> Result<()> {
+        let size = [img.width(), img.height()];
+        self.get_texture(&resource)
+            .upload_part_raw(GenMipmaps::No, offset, size, img.as_raw())?;
+        Ok(())
     }
 }
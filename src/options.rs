@@ -0,0 +1,264 @@
+use crate::{
+    audio::Channel,
+    controls::{Controls, GameAction, PROFILES, REBINDABLE},
+    game::GameMode,
+    locale::Locale,
+    menu::{Menu, MenuCommand, MenuEvent, MenuItem, MenuTree, MenuValue, Slider},
+    settings::{Settings, WindowMode},
+};
+
+const VOLUME_STEP: f32 = 0.1;
+
+const CHANNELS: [(Channel, &str); 3] = [
+    (Channel::Master, "audio.master"),
+    (Channel::Music, "audio.music"),
+    (Channel::Effects, "audio.effects"),
+];
+
+// Everything the menus show besides their layout
+pub struct MenuContext<'a> {
+    pub settings: &'a Settings,
+    pub controls: &'a Controls,
+    // Names of the connected monitors
+    pub monitors: &'a [Option<String>],
+    // Codes and names of the available languages
+    pub languages: &'a [(String, String)],
+    pub locale: &'a Locale,
+}
+
+fn mode_name(locale: &Locale, mode: GameMode) -> String {
+    let key = format!("mode.{:?}", mode).to_lowercase();
+    locale.get(&key).to_string()
+}
+
+fn window_mode_name(locale: &Locale, mode: WindowMode) -> String {
+    let key = format!("window_mode.{:?}", mode).to_lowercase();
+    locale.get(&key).to_string()
+}
+
+// Names of the keys and buttons bound to the action, as shown in menus
+pub fn keys_text(controls: &Controls, action: GameAction, locale: &Locale) -> String {
+    let names: Vec<_> = controls
+        .names_for(action)
+        .into_iter()
+        .map(str::to_uppercase)
+        .collect();
+    if names.is_empty() {
+        locale.get("controls.none").to_string()
+    } else {
+        names.join(" ")
+    }
+}
+
+// The title screen, with `mode` preselected
+pub fn main_menu(ctx: &MenuContext, mode: GameMode) -> Menu {
+    let locale = ctx.locale;
+    let modes = GameMode::ALL
+        .iter()
+        .map(|&m| mode_name(locale, m))
+        .collect();
+    let mode_idx = GameMode::ALL.iter().position(|&m| m == mode).unwrap();
+    Menu::new(
+        locale.get("menu.title"),
+        vec![
+            MenuItem::button(locale.get("menu.start"), MenuCommand::Start),
+            MenuItem::choice(locale.get("menu.mode"), MenuCommand::Mode, modes, mode_idx),
+            MenuItem::button(locale.get("menu.scores"), MenuCommand::HighScores),
+            MenuItem::submenu(options_menu(ctx)),
+            MenuItem::button(locale.get("menu.quit"), MenuCommand::Quit),
+        ],
+    )
+}
+
+// Shown over the frozen game, the options are the same as on the title screen
+pub fn pause_menu(ctx: &MenuContext) -> Menu {
+    let locale = ctx.locale;
+    Menu::new(
+        locale.get("pause.title"),
+        vec![
+            MenuItem::button(locale.get("pause.resume"), MenuCommand::Resume),
+            MenuItem::button(locale.get("pause.restart"), MenuCommand::Restart),
+            MenuItem::submenu(options_menu(ctx)),
+            MenuItem::button(locale.get("pause.quit_to_menu"), MenuCommand::QuitToMenu),
+        ],
+    )
+}
+
+pub fn options_menu(ctx: &MenuContext) -> Menu {
+    let locale = ctx.locale;
+    let mut items = vec![
+        MenuItem::submenu(display_menu(ctx)),
+        MenuItem::submenu(audio_menu(ctx)),
+        MenuItem::submenu(controls_menu(ctx)),
+    ];
+    if !ctx.languages.is_empty() {
+        let names = ctx.languages.iter().map(|(_, name)| name.clone()).collect();
+        let language_idx = ctx
+            .languages
+            .iter()
+            .position(|(code, _)| code == locale.language())
+            .unwrap_or(0);
+        items.push(MenuItem::choice(
+            locale.get("options.language"),
+            MenuCommand::Language,
+            names,
+            language_idx,
+        ));
+    }
+    items.push(MenuItem::back(locale.get("menu.back")));
+    Menu::new(locale.get("options.title"), items)
+}
+
+fn display_menu(ctx: &MenuContext) -> Menu {
+    let locale = ctx.locale;
+    let display = &ctx.settings.display;
+    let resolutions = display.resolutions();
+    let current = (display.width, display.height);
+    let resolution_idx = resolutions.iter().position(|&r| r == current).unwrap();
+    let resolutions = resolutions
+        .iter()
+        .map(|(w, h)| format!("{}x{}", w, h))
+        .collect();
+    let modes = WindowMode::ALL
+        .iter()
+        .map(|&m| window_mode_name(locale, m))
+        .collect();
+    let mode_idx = WindowMode::ALL
+        .iter()
+        .position(|&m| m == display.window_mode)
+        .unwrap();
+    let mut items = vec![
+        MenuItem::choice(
+            locale.get("display.window"),
+            MenuCommand::WindowMode,
+            modes,
+            mode_idx,
+        ),
+        MenuItem::choice(
+            locale.get("display.resolution"),
+            MenuCommand::Resolution,
+            resolutions,
+            resolution_idx,
+        ),
+    ];
+    if !ctx.monitors.is_empty() {
+        let names = ctx
+            .monitors
+            .iter()
+            .enumerate()
+            .map(|(i, name)| match name {
+                Some(name) => name.to_uppercase(),
+                None => locale.format(
+                    "display.monitor_number",
+                    &[("n", &locale.number(i as u32 + 1))],
+                ),
+            })
+            .collect();
+        let monitor_idx = display.monitor.min(ctx.monitors.len() - 1);
+        items.push(MenuItem::choice(
+            locale.get("display.monitor"),
+            MenuCommand::Monitor,
+            names,
+            monitor_idx,
+        ));
+    }
+    items.push(MenuItem::toggle(
+        locale.get("display.vsync"),
+        MenuCommand::VSync,
+        display.vsync,
+    ));
+    items.push(MenuItem::back(locale.get("menu.back")));
+    Menu::new(locale.get("display.title"), items)
+}
+
+fn audio_menu(ctx: &MenuContext) -> Menu {
+    let (locale, audio) = (ctx.locale, &ctx.settings.audio);
+    let sliders = CHANNELS.iter().map(|&(channel, key)| {
+        let slider = Slider {
+            value: audio.channel(channel).volume,
+            min: 0.,
+            max: 1.,
+            step: VOLUME_STEP,
+        };
+        MenuItem::slider(locale.get(key), MenuCommand::Volume(channel), slider)
+    });
+    let mutes = CHANNELS.iter().map(|&(channel, key)| {
+        let muted = audio.channel(channel).muted;
+        MenuItem::toggle(
+            &locale.format("audio.mute", &[("channel", locale.get(key))]),
+            MenuCommand::Mute(channel),
+            muted,
+        )
+    });
+    let items = sliders
+        .chain(mutes)
+        .chain(Some(MenuItem::back(locale.get("menu.back"))))
+        .collect();
+    Menu::new(locale.get("audio.title"), items)
+}
+
+fn controls_menu(ctx: &MenuContext) -> Menu {
+    let locale = ctx.locale;
+    let profiles = PROFILES
+        .iter()
+        .enumerate()
+        .map(|(i, (key, _))| MenuItem::button(locale.get(key), MenuCommand::LoadProfile(i)))
+        .chain(Some(MenuItem::back(locale.get("menu.back"))))
+        .collect();
+    let rebinds = REBINDABLE.iter().map(|&action| {
+        MenuItem::button(locale.get(action.text_key()), MenuCommand::Rebind(action))
+            .with_detail(keys_text(ctx.controls, action, locale))
+    });
+    let profiles = Menu::new(locale.get("controls.profiles"), profiles);
+    let items = Some(MenuItem::submenu(profiles))
+        .into_iter()
+        .chain(rebinds)
+        .chain(Some(MenuItem::back(locale.get("menu.back"))))
+        .collect();
+    Menu::new(locale.get("controls.title"), items)
+}
+
+// Shows the current bindings after they were changed
+pub fn update_controls_menu(menu: &mut MenuTree, controls: &Controls, locale: &Locale) {
+    for &action in &REBINDABLE {
+        if let Some(item) = menu.item_mut(MenuCommand::Rebind(action)) {
+            item.detail = Some(keys_text(controls, action, locale));
+        }
+    }
+}
+
+// Stores a menu change in the settings, returning whether anything changed.
+// `languages` are the ones listed in the menu.
+pub fn apply(event: MenuEvent, settings: &mut Settings, languages: &[(String, String)]) -> bool {
+    let (command, value) = match event {
+        MenuEvent::Changed(command, value) => (command, value),
+        _ => return false,
+    };
+    match (command, value) {
+        (MenuCommand::Mode, MenuValue::Index(i)) => settings.mode = GameMode::ALL[i],
+        // A custom resolution is no longer listed once another one is picked
+        (MenuCommand::Resolution, MenuValue::Index(i)) => {
+            match settings.display.resolutions().get(i) {
+                Some(&(width, height)) => {
+                    settings.display.width = width;
+                    settings.display.height = height;
+                }
+                None => return false,
+            }
+        }
+        (MenuCommand::WindowMode, MenuValue::Index(i)) => {
+            settings.display.window_mode = WindowMode::ALL[i]
+        }
+        (MenuCommand::Monitor, MenuValue::Index(i)) => settings.display.monitor = i,
+        (MenuCommand::VSync, MenuValue::Bool(b)) => settings.display.vsync = b,
+        (MenuCommand::Language, MenuValue::Index(i)) => settings.language = languages[i].0.clone(),
+        (MenuCommand::Volume(channel), MenuValue::Number(v)) => {
+            settings.audio.channel_mut(channel).volume = v
+        }
+        (MenuCommand::Mute(channel), MenuValue::Bool(b)) => {
+            settings.audio.channel_mut(channel).muted = b
+        }
+        _ => return false,
+    }
+    true
+}
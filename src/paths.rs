@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+
+const APP_DIR: &str = "lumberman";
+
+fn app_dir(base: Option<PathBuf>) -> PathBuf {
+    base.unwrap_or_else(|| PathBuf::from(".")).join(APP_DIR)
+}
+
+// These fall back to the working directory on platforms without such directories
+pub fn data_file(name: &str) -> PathBuf {
+    app_dir(dirs::data_dir()).join(name)
+}
+
+pub fn config_file(name: &str) -> PathBuf {
+    app_dir(dirs::config_dir()).join(name)
+}
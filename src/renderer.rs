@@ -0,0 +1,360 @@
+use crate::{
+    error::Result,
+    object::{Color, Object, ResourceManager, TessResource, TextureResource, VertexIndex, WHITE},
+    semantics::{Semantics, ShaderInterface, UiVertex, VertexColor, VertexPosition, VertexUV},
+};
+use luminance::{pipeline::Render, tess::View};
+use luminance_front::{
+    blending::{Blending, Equation, Factor},
+    context::GraphicsContext,
+    depth_test::DepthWrite,
+    framebuffer::Framebuffer,
+    pipeline::{PipelineError, PipelineState},
+    render_state::RenderState,
+    shader::Program,
+    tess::{Interleaved, Mode, Tess},
+    texture::Dim2,
+    Backend,
+};
+use rapier3d::na::{Matrix4, Vector4};
+
+const VS_STR: &str = include_str!("vs.glsl");
+const FS_STR: &str = include_str!("fs.glsl");
+
+const UI_VS_STR: &str = include_str!("ui_vs.glsl");
+const UI_FS_STR: &str = include_str!("ui_fs.glsl");
+
+// Quads the UI buffer starts with, it doubles whenever a frame needs more
+const INITIAL_QUADS: usize = 1024;
+
+type QuadTess = Tess<UiVertex, VertexIndex, (), Interleaved>;
+
+// A textured rectangle in the xy-plane, drawn with the UI shader
+#[derive(Debug, Clone, Copy)]
+pub struct Quad {
+    pub texture: TextureResource,
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub color: Color,
+}
+
+impl Quad {
+    // The whole texture on a unit square centered on the origin
+    pub fn unit(texture: TextureResource) -> Self {
+        Self {
+            texture,
+            min: [-0.5, -0.5],
+            max: [0.5, 0.5],
+            uv_min: [0., 0.],
+            uv_max: [1., 1.],
+            color: WHITE,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SceneDraw {
+    texture: TextureResource,
+    tess: TessResource,
+    transform: Matrix4<f32>,
+}
+
+// Consecutive quads with the same texture, drawn with one call
+#[derive(Debug, Clone, Copy)]
+struct Batch {
+    texture: TextureResource,
+    start: usize,
+    count: usize,
+}
+
+// Quads in the order they were pushed, which is the order they are drawn in
+#[derive(Default)]
+struct QuadLayer {
+    vertices: Vec<UiVertex>,
+    batches: Vec<Batch>,
+}
+
+impl QuadLayer {
+    fn len(&self) -> usize {
+        self.vertices.len() / 4
+    }
+
+    fn clear(&mut self) {
+        self.vertices.clear();
+        self.batches.clear();
+    }
+
+    fn push(&mut self, quad: &Quad, transform: &Matrix4<f32>) {
+        let start = self.len();
+        match self.batches.last_mut() {
+            Some(batch) if batch.texture == quad.texture => batch.count += 1,
+            _ => self.batches.push(Batch {
+                texture: quad.texture,
+                start,
+                count: 1,
+            }),
+        }
+        // Same corner order as `geometry::quad`
+        let corners = [
+            (quad.min[0], quad.max[1], quad.uv_min[0], quad.uv_max[1]),
+            (quad.max[0], quad.max[1], quad.uv_max[0], quad.uv_max[1]),
+            (quad.min[0], quad.min[1], quad.uv_min[0], quad.uv_min[1]),
+            (quad.max[0], quad.min[1], quad.uv_max[0], quad.uv_min[1]),
+        ];
+        for &(x, y, u, v) in &corners {
+            let position = transform * Vector4::new(x, y, 0., 1.);
+            self.vertices.push(UiVertex::new(
+                VertexPosition::new([position.x, position.y, position.z]),
+                VertexUV::new([u, v]),
+                VertexColor::new(quad.color),
+            ));
+        }
+    }
+}
+
+// Everything drawn in a frame. The background is drawn first, then the scene
+// and the UI over it. Keeping the list between frames reuses its buffers.
+pub struct DrawList {
+    background: QuadLayer,
+    // Sorted by texture and tess before drawing
+    opaque: Vec<SceneDraw>,
+    // Drawn after the opaque objects in the order they were pushed
+    blended: Vec<SceneDraw>,
+    ui: QuadLayer,
+    // From UI coordinates to clip space
+    ui_transform: Matrix4<f32>,
+}
+
+impl Default for DrawList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DrawList {
+    pub fn new() -> Self {
+        Self {
+            background: QuadLayer::default(),
+            opaque: vec![],
+            blended: vec![],
+            ui: QuadLayer::default(),
+            ui_transform: Matrix4::identity(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.background.clear();
+        self.opaque.clear();
+        self.blended.clear();
+        self.ui.clear();
+    }
+
+    // A quad in clip space behind everything else
+    pub fn background(&mut self, quad: &Quad) {
+        self.background.push(quad, &Matrix4::identity());
+    }
+
+    pub fn model(&mut self, model: &[Object], transform: &Matrix4<f32>) {
+        self.opaque.extend(model.iter().map(|object| SceneDraw {
+            texture: object.texture,
+            tess: object.tess,
+            transform: transform * object.transform,
+        }));
+    }
+
+    // A scene object with transparent parts, which must not hide what is
+    // drawn after it
+    pub fn sprite(&mut self, object: &Object, transform: &Matrix4<f32>) {
+        self.blended.push(SceneDraw {
+            texture: object.texture,
+            tess: object.tess,
+            transform: transform * object.transform,
+        });
+    }
+
+    pub fn set_ui_transform(&mut self, transform: Matrix4<f32>) {
+        self.ui_transform = transform;
+    }
+
+    // A quad in UI coordinates, drawn over the scene and earlier quads
+    pub fn quad(&mut self, quad: &Quad, transform: &Matrix4<f32>) {
+        self.ui.push(quad, &(self.ui_transform * transform));
+    }
+}
+
+// Indices of two triangles for each quad, matching the corners of `QuadLayer`
+fn quad_indices(quads: usize) -> Vec<VertexIndex> {
+    (0..quads as VertexIndex)
+        .flat_map(|q| [0, 1, 2, 1, 2, 3].iter().map(move |i| 4 * q + i))
+        .collect()
+}
+
+fn make_quad_tess(
+    ctxt: &mut impl GraphicsContext<Backend = Backend>,
+    quads: usize,
+) -> Result<QuadTess> {
+    let empty = UiVertex::new(
+        VertexPosition::new([0.; 3]),
+        VertexUV::new([0.; 2]),
+        VertexColor::new([0.; 4]),
+    );
+    Ok(ctxt
+        .new_tess()
+        .set_mode(Mode::Triangle)
+        .set_vertices(vec![empty; 4 * quads])
+        .set_indices(quad_indices(quads))
+        .build()?)
+}
+
+// Draws a `DrawList` with one texture binding per group of scene objects and
+// one draw call per UI batch
+pub struct Renderer {
+    program: Program<Semantics, (), ShaderInterface>,
+    ui_program: Program<Semantics, (), ShaderInterface>,
+    // Background quads followed by UI quads
+    quads: QuadTess,
+    capacity: usize,
+}
+
+impl Renderer {
+    pub fn new(ctxt: &mut impl GraphicsContext<Backend = Backend>) -> Result<Self> {
+        let program = ctxt
+            .new_shader_program::<Semantics, (), ShaderInterface>()
+            .from_strings(VS_STR, None, None, FS_STR)?
+            .ignore_warnings();
+
+        let ui_program = ctxt
+            .new_shader_program::<Semantics, (), ShaderInterface>()
+            .from_strings(UI_VS_STR, None, None, UI_FS_STR)?
+            .ignore_warnings();
+
+        Ok(Self {
+            program,
+            ui_program,
+            quads: make_quad_tess(ctxt, INITIAL_QUADS)?,
+            capacity: INITIAL_QUADS,
+        })
+    }
+
+    // Sorts the scene and uploads the quads, before `render`
+    pub fn prepare(
+        &mut self,
+        ctxt: &mut impl GraphicsContext<Backend = Backend>,
+        list: &mut DrawList,
+    ) -> Result<()> {
+        list.opaque
+            .sort_unstable_by_key(|draw| (draw.texture, draw.tess));
+
+        let quads = list.background.len() + list.ui.len();
+        if quads > self.capacity {
+            self.capacity = quads.max(2 * self.capacity);
+            self.quads = make_quad_tess(ctxt, self.capacity)?;
+        }
+        if quads == 0 {
+            return Ok(());
+        }
+        let mut vertices = self.quads.vertices_mut()?;
+        let (background, ui) = vertices.split_at_mut(list.background.vertices.len());
+        background.copy_from_slice(&list.background.vertices);
+        ui[..list.ui.vertices.len()].copy_from_slice(&list.ui.vertices);
+        Ok(())
+    }
+
+    pub fn render(
+        &mut self,
+        ctxt: &mut impl GraphicsContext<Backend = Backend>,
+        back_buffer: &Framebuffer<Dim2, (), ()>,
+        rm: &mut ResourceManager,
+        list: &DrawList,
+        projection: &Matrix4<f32>,
+        view: &Matrix4<f32>,
+    ) -> Render<PipelineError> {
+        let Self {
+            program,
+            ui_program,
+            quads,
+            ..
+        } = self;
+        let blending = Blending {
+            equation: Equation::Additive,
+            src: Factor::SrcAlpha,
+            dst: Factor::SrcAlphaComplement,
+        };
+        let scene_st = &RenderState::default().set_blending(blending);
+        // Quads are drawn in order, so later ones must not be rejected by
+        // the depth of earlier ones
+        let quad_st = &RenderState::default()
+            .set_blending(blending)
+            .set_depth_test(None)
+            .set_depth_write(DepthWrite::Off);
+        let ui_offset = list.background.len();
+
+        ctxt.new_pipeline_gate()
+            .pipeline(
+                back_buffer,
+                &PipelineState::default(),
+                |pipeline, mut shd_gate| {
+                    shd_gate.shade(ui_program, |mut iface, uni, mut rdr_gate| {
+                        rdr_gate.render(quad_st, |mut tess_gate| {
+                            list.background.batches.iter().try_for_each(|batch| {
+                                let bound_tex =
+                                    pipeline.bind_texture(rm.get_texture(&batch.texture))?;
+                                iface.set(&uni.tex, bound_tex.binding());
+                                tess_gate.render(batch_view(quads, batch, 0))
+                            })
+                        })
+                    })?;
+                    shd_gate.shade(program, |mut iface, uni, mut rdr_gate| {
+                        iface.set(&uni.projection, (*projection).into());
+                        iface.set(&uni.view, (*view).into());
+                        rdr_gate.render(scene_st, |mut tess_gate| {
+                            for draws in [&list.opaque, &list.blended].iter() {
+                                let mut start = 0;
+                                while start < draws.len() {
+                                    let texture = draws[start].texture;
+                                    let end = draws[start..]
+                                        .iter()
+                                        .position(|draw| draw.texture != texture)
+                                        .map_or(draws.len(), |n| start + n);
+                                    let bound_tex =
+                                        pipeline.bind_texture(rm.get_texture(&texture))?;
+                                    iface.set(&uni.tex, bound_tex.binding());
+                                    for draw in &draws[start..end] {
+                                        iface.set(&uni.model, draw.transform.into());
+                                        tess_gate.render(rm.get_tess(&draw.tess))?;
+                                    }
+                                    start = end;
+                                }
+                            }
+                            Ok(())
+                        })
+                    })?;
+                    shd_gate.shade(ui_program, |mut iface, uni, mut rdr_gate| {
+                        rdr_gate.render(quad_st, |mut tess_gate| {
+                            list.ui.batches.iter().try_for_each(|batch| {
+                                let bound_tex =
+                                    pipeline.bind_texture(rm.get_texture(&batch.texture))?;
+                                iface.set(&uni.tex, bound_tex.binding());
+                                tess_gate.render(batch_view(quads, batch, ui_offset))
+                            })
+                        })
+                    })
+                },
+            )
+            .assume()
+    }
+}
+
+// The indices of a batch whose layer starts `offset` quads into the buffer
+fn batch_view<'a>(
+    quads: &'a QuadTess,
+    batch: &Batch,
+    offset: usize,
+) -> luminance_front::tess::TessView<'a, UiVertex, VertexIndex, (), Interleaved> {
+    let start = 6 * (offset + batch.start);
+    quads
+        .view(start..start + 6 * batch.count)
+        .expect("batches are within the uploaded quads")
+}
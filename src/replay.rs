@@ -0,0 +1,231 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{controls::GameAction, game::GameMode};
+
+const MAGIC: &[u8; 4] = b"LMBR";
+const VERSION: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayEvent {
+    pub time: Duration,
+    pub action: GameAction,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub mode: GameMode,
+    pub events: Vec<ReplayEvent>,
+}
+
+fn action_to_byte(action: GameAction) -> u8 {
+    match action {
+        GameAction::Left => 0,
+        GameAction::Right => 1,
+        GameAction::Down => 2,
+        GameAction::Up => 3,
+        GameAction::Enter => 4,
+        GameAction::Back => 5,
+    }
+}
+
+fn action_from_byte(byte: u8) -> io::Result<GameAction> {
+    match byte {
+        0 => Ok(GameAction::Left),
+        1 => Ok(GameAction::Right),
+        2 => Ok(GameAction::Down),
+        3 => Ok(GameAction::Up),
+        4 => Ok(GameAction::Enter),
+        5 => Ok(GameAction::Back),
+        _ => Err(invalid_data("unknown action")),
+    }
+}
+
+fn mode_to_byte(mode: GameMode) -> u8 {
+    GameMode::ALL.iter().position(|&m| m == mode).unwrap() as u8
+}
+
+fn mode_from_byte(byte: u8) -> io::Result<GameMode> {
+    GameMode::ALL
+        .get(byte as usize)
+        .copied()
+        .ok_or_else(|| invalid_data("unknown game mode"))
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+impl Replay {
+    pub fn new(seed: u64, mode: GameMode) -> Self {
+        Self {
+            seed,
+            mode,
+            events: vec![],
+        }
+    }
+
+    // Layout: magic, version, seed, mode, event count, then (nanos, action) per event
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&[mode_to_byte(self.mode)])?;
+        writer.write_all(&(self.events.len() as u32).to_le_bytes())?;
+        for e in &self.events {
+            writer.write_all(&(e.time.as_nanos() as u64).to_le_bytes())?;
+            writer.write_all(&[action_to_byte(e.action)])?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from(reader: &mut impl Read) -> io::Result<Self> {
+        if &read_array::<4>(reader)? != MAGIC {
+            return Err(invalid_data("not a replay file"));
+        }
+        if read_array::<1>(reader)?[0] != VERSION {
+            return Err(invalid_data("unsupported replay version"));
+        }
+        let seed = u64::from_le_bytes(read_array(reader)?);
+        let mode = mode_from_byte(read_array::<1>(reader)?[0])?;
+        let count = u32::from_le_bytes(read_array(reader)?);
+        let events = (0..count)
+            .map(|_| {
+                Ok(ReplayEvent {
+                    time: Duration::from_nanos(u64::from_le_bytes(read_array(reader)?)),
+                    action: action_from_byte(read_array::<1>(reader)?[0])?,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self { seed, mode, events })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+}
+
+pub struct Recorder {
+    path: PathBuf,
+    replay: Option<Replay>,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, replay: None }
+    }
+
+    pub fn start(&mut self, seed: u64, mode: GameMode) {
+        self.replay = Some(Replay::new(seed, mode));
+    }
+
+    pub fn record(&mut self, time: Duration, action: GameAction) {
+        if let Some(replay) = &mut self.replay {
+            replay.events.push(ReplayEvent { time, action });
+        }
+    }
+
+    // Writes the current run to disk, overwriting the previous one
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.replay.take() {
+            Some(replay) => replay.save(&self.path),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct Playback {
+    replay: Replay,
+    next: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self { replay, next: 0 }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    pub fn mode(&self) -> GameMode {
+        self.replay.mode
+    }
+
+    // Returns the time step to take from `time` and the action at the end of it.
+    // The step is shortened so that recorded actions land on their exact run time.
+    pub fn next(&mut self, time: Duration, dt: Duration) -> (Duration, Option<GameAction>) {
+        match self.replay.events.get(self.next) {
+            Some(e) if e.time <= time + dt => {
+                self.next += 1;
+                (e.time.saturating_sub(time), Some(e.action))
+            }
+            _ => (dt, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Replay {
+        let mut replay = Replay::new(0xdead_beef, GameMode::Expert);
+        for (millis, action) in [(250, GameAction::Left), (600, GameAction::Right)].iter() {
+            replay.events.push(ReplayEvent {
+                time: Duration::from_millis(*millis),
+                action: *action,
+            });
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut bytes = vec![];
+        sample().write_to(&mut bytes).unwrap();
+        assert_eq!(Replay::read_from(&mut bytes.as_slice()).unwrap(), sample());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let mut bytes = vec![];
+        sample().write_to(&mut bytes).unwrap();
+        bytes[4] = VERSION + 1;
+        assert!(Replay::read_from(&mut bytes.as_slice()).is_err());
+        assert!(Replay::read_from(&mut &b"PNG\0"[..]).is_err());
+        bytes[4] = VERSION;
+        bytes.pop();
+        assert!(Replay::read_from(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn playback_lands_on_recorded_times() {
+        let mut playback = Playback::new(sample());
+        let frame = Duration::from_millis(400);
+        let step = playback.next(Duration::ZERO, frame);
+        assert_eq!(step, (Duration::from_millis(250), Some(GameAction::Left)));
+        let step = playback.next(Duration::from_millis(250), Duration::from_millis(150));
+        assert_eq!(step, (Duration::from_millis(150), None));
+        let step = playback.next(Duration::from_millis(400), frame);
+        assert_eq!(step, (Duration::from_millis(200), Some(GameAction::Right)));
+        assert_eq!(playback.next(Duration::from_secs(1), frame), (frame, None));
+    }
+}
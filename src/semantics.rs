@@ -23,6 +23,8 @@ pub enum Semantics {
     Normal,
     #[sem(name = "uv", repr = "[f32; 2]", wrapper = "VertexUV")]
     UV,
+    #[sem(name = "color", repr = "[f32; 4]", wrapper = "VertexColor")]
+    Color,
 }
 
 #[derive(Clone, Copy, Debug, Vertex)]
@@ -32,3 +34,14 @@ pub struct Vertex {
     normal: VertexNormal,
     uv: VertexUV,
 }
+
+// UI quads are transformed on the CPU and drawn in batches, so each vertex
+// carries its own colour. The fields are only read by the shader.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Vertex)]
+#[vertex(sem = "Semantics")]
+pub struct UiVertex {
+    position: VertexPosition,
+    uv: VertexUV,
+    color: VertexColor,
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{
+    assets,
+    audio::VolumeSettings,
+    controls::{Controls, GameAction},
+    game::GameMode,
+    locale::DEFAULT_LANGUAGE,
+    paths,
+};
+
+const FILE_NAME: &str = "settings.json";
+
+pub const RESOLUTIONS: [(u32, u32); 5] = [
+    (960, 540),
+    (1280, 720),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowMode {
+    Windowed,
+    // Changes the video mode of the monitor to the chosen resolution
+    Fullscreen,
+    // An undecorated window covering the monitor at its current video mode
+    Borderless,
+}
+
+impl WindowMode {
+    pub const ALL: [WindowMode; 3] = [
+        WindowMode::Windowed,
+        WindowMode::Fullscreen,
+        WindowMode::Borderless,
+    ];
+}
+
+impl FromStr for WindowMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WindowMode::ALL
+            .iter()
+            .copied()
+            .find(|m| format!("{:?}", m).eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("unknown window mode: {}", s))
+    }
+}
+
+// The size is used for windowed and fullscreen modes, it does not have to be
+// one of `RESOLUTIONS`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub width: u32,
+    pub height: u32,
+    pub window_mode: WindowMode,
+    // Index in the list of connected monitors, the primary one is first
+    pub monitor: usize,
+    pub vsync: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            width: 960,
+            height: 540,
+            window_mode: WindowMode::Windowed,
+            monitor: 0,
+            vsync: true,
+        }
+    }
+}
+
+impl DisplaySettings {
+    // The preset resolutions, followed by the current one if it is custom
+    pub fn resolutions(&self) -> Vec<(u32, u32)> {
+        let mut resolutions = RESOLUTIONS.to_vec();
+        let current = (self.width, self.height);
+        if !resolutions.contains(&current) {
+            resolutions.push(current);
+        }
+        resolutions
+    }
+}
+
+// Missing fields take their default value, so older files keep loading
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub display: DisplaySettings,
+    pub audio: VolumeSettings,
+    pub mode: GameMode,
+    // Code of the language, the name of its string table
+    pub language: String,
+    // Key names mapped to the action they trigger
+    pub bindings: BTreeMap<String, GameAction>,
+    // A chop coming sooner than this after the previous one waits, 0 to disable
+    pub input_lock_ms: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            display: DisplaySettings::default(),
+            audio: VolumeSettings::default(),
+            mode: GameMode::Classic,
+            language: DEFAULT_LANGUAGE.to_string(),
+            bindings: Controls::default().to_names(),
+            input_lock_ms: 0,
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> PathBuf {
+        paths::config_file(FILE_NAME)
+    }
+
+    // Falls back to the defaults if there is no usable settings file
+    pub fn load() -> Self {
+        let path = Self::path();
+        if !path.exists() {
+            return Self::default();
+        }
+        let mut settings: Self = assets::load_json(&path).unwrap_or_else(|e| {
+            eprintln!("{}\nusing default settings", e);
+            Self::default()
+        });
+        settings.audio.clamp();
+        settings
+    }
+
+    pub fn input_lock(&self) -> Duration {
+        Duration::from_millis(self.input_lock_ms)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,366 @@
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use statrs::distribution::Normal;
+use std::{collections::BTreeMap, time::Duration};
+
+use crate::{
+    difficulty::DifficultyProfile,
+    game::{Branch, Game, GameEvent, GameRng, PlayerAction},
+    game_physics::GamePhysics,
+};
+
+pub trait Policy {
+    // Chooses the next chop and how long after the previous one it happens
+    fn decide(&mut self, game: &Game) -> (PlayerAction, Duration);
+}
+
+fn safe_action(game: &Game) -> PlayerAction {
+    let blocked = |b: Branch| game.tree.iter().take(2).any(|&x| x == b);
+    if blocked(Branch::Left) {
+        PlayerAction::ChopRight
+    } else {
+        PlayerAction::ChopLeft
+    }
+}
+
+fn opposite(action: PlayerAction) -> PlayerAction {
+    match action {
+        PlayerAction::ChopLeft => PlayerAction::ChopRight,
+        PlayerAction::ChopRight => PlayerAction::ChopLeft,
+    }
+}
+
+// Never hits a branch, only the timer can end the game
+pub struct PerfectPolicy {
+    pub reaction: Duration,
+}
+
+impl Policy for PerfectPolicy {
+    fn decide(&mut self, game: &Game) -> (PlayerAction, Duration) {
+        (safe_action(game), self.reaction)
+    }
+}
+
+pub struct RandomPolicy {
+    pub reaction: Duration,
+    rng: GameRng,
+}
+
+impl RandomPolicy {
+    pub fn new(reaction: Duration, seed: u64) -> Self {
+        Self {
+            reaction,
+            rng: GameRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Policy for RandomPolicy {
+    fn decide(&mut self, _game: &Game) -> (PlayerAction, Duration) {
+        let action = if self.rng.gen() {
+            PlayerAction::ChopLeft
+        } else {
+            PlayerAction::ChopRight
+        };
+        (action, self.reaction)
+    }
+}
+
+// Plays safely but with a normally distributed reaction time and an error rate
+pub struct HumanPolicy {
+    reaction: Normal,
+    error_rate: f64,
+    rng: GameRng,
+}
+
+impl HumanPolicy {
+    pub fn new(mean_reaction: f64, std_dev: f64, error_rate: f64, seed: u64) -> Self {
+        Self {
+            reaction: Normal::new(mean_reaction, std_dev).unwrap(),
+            error_rate,
+            rng: GameRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Policy for HumanPolicy {
+    fn decide(&mut self, game: &Game) -> (PlayerAction, Duration) {
+        let mut action = safe_action(game);
+        if self.rng.gen_bool(self.error_rate) {
+            action = opposite(action);
+        }
+        let reaction = self.rng.sample(self.reaction).max(0.);
+        (action, Duration::from_secs_f64(reaction))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum DeathCause {
+    Branch,
+    TimeUp,
+    ChopLimit,
+}
+
+pub struct GameResult {
+    pub score: u32,
+    pub cause: DeathCause,
+    pub duration: Duration,
+    pub branches: BranchStats,
+    pub physics_steps: u64,
+}
+
+#[derive(Clone, Default, Serialize)]
+pub struct BranchStats {
+    pub segments: u64,
+    pub left: u64,
+    pub right: u64,
+    pub longest_gap: u32,
+}
+
+impl BranchStats {
+    fn observe(&mut self, branch: Branch, gap: &mut u32) {
+        self.segments += 1;
+        match branch {
+            Branch::None => {
+                *gap += 1;
+                self.longest_gap = self.longest_gap.max(*gap);
+            }
+            Branch::Left => {
+                self.left += 1;
+                *gap = 0;
+            }
+            Branch::Right => {
+                self.right += 1;
+                *gap = 0;
+            }
+        }
+    }
+
+    fn merge(&mut self, other: &BranchStats) {
+        self.segments += other.segments;
+        self.left += other.left;
+        self.right += other.right;
+        self.longest_gap = self.longest_gap.max(other.longest_gap);
+    }
+}
+
+pub struct Simulation {
+    pub profile: DifficultyProfile,
+    pub max_chops: u32,
+    pub physics: bool,
+}
+
+impl Simulation {
+    pub fn run_game(&self, policy: &mut impl Policy, seed: u64) -> GameResult {
+        let mut game = Game::with_seed(self.profile.clone(), seed);
+        let mut physics = if self.physics {
+            Some(GamePhysics::with_seed(seed))
+        } else {
+            None
+        };
+        // Only grown segments count, the initial tree is empty
+        let mut branches = BranchStats::default();
+        let mut gap = 0;
+        let mut duration = Duration::ZERO;
+        let mut physics_steps = 0;
+
+        let (score, cause) = loop {
+            if game.get_score() >= self.max_chops {
+                break (game.get_score(), DeathCause::ChopLimit);
+            }
+            let (action, delay) = policy.decide(&game);
+            duration += delay;
+            if let Some(physics) = &mut physics {
+                physics_steps += physics.advance(delay) as u64;
+            }
+            if let Some(GameEvent::TimeUp(score)) = game.tick(delay) {
+                break (score, DeathCause::TimeUp);
+            }
+            let grown = game.segments_grown();
+            let event = game.update(action);
+            if let Some(physics) = &mut physics {
+                physics.update(&game, action);
+            }
+            let new = (game.segments_grown() - grown) as usize;
+            for &b in game.tree.iter().skip(game.tree.len() - new) {
+                branches.observe(b, &mut gap);
+            }
+            if let GameEvent::Finished(score) = event {
+                break (score, DeathCause::Branch);
+            }
+        };
+
+        GameResult {
+            score,
+            cause,
+            duration,
+            branches,
+            physics_steps,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScoreSummary {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: u32,
+    pub max: u32,
+    pub percentiles: BTreeMap<u32, u32>,
+    pub histogram: BTreeMap<u32, u32>,
+}
+
+#[derive(Default, Serialize)]
+pub struct DeathCauses {
+    pub branch: u32,
+    pub time_up: u32,
+    pub chop_limit: u32,
+}
+
+#[derive(Serialize)]
+pub struct Report {
+    pub games: usize,
+    pub scores: ScoreSummary,
+    pub death_causes: DeathCauses,
+    pub mean_duration_secs: f64,
+    pub branches: BranchStats,
+    pub physics_steps: u64,
+}
+
+impl Report {
+    pub fn new(results: &[GameResult], bucket_size: u32) -> Self {
+        let mut scores: Vec<u32> = results.iter().map(|r| r.score).collect();
+        scores.sort_unstable();
+        let n = scores.len().max(1) as f64;
+        let mean = scores.iter().map(|&s| s as f64).sum::<f64>() / n;
+        let variance = scores
+            .iter()
+            .map(|&s| (s as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        let percentiles = match scores.len() {
+            0 => BTreeMap::new(),
+            len => [10, 25, 50, 75, 90, 99]
+                .iter()
+                .map(|&p| (p, scores[(len - 1) * p as usize / 100]))
+                .collect(),
+        };
+        let mut histogram = BTreeMap::new();
+        for &s in &scores {
+            *histogram.entry(s / bucket_size * bucket_size).or_insert(0) += 1;
+        }
+
+        let mut death_causes = DeathCauses::default();
+        let mut branches = BranchStats::default();
+        for r in results {
+            match r.cause {
+                DeathCause::Branch => death_causes.branch += 1,
+                DeathCause::TimeUp => death_causes.time_up += 1,
+                DeathCause::ChopLimit => death_causes.chop_limit += 1,
+            }
+            branches.merge(&r.branches);
+        }
+
+        Self {
+            games: results.len(),
+            scores: ScoreSummary {
+                mean,
+                std_dev: variance.sqrt(),
+                min: scores.first().copied().unwrap_or(0),
+                max: scores.last().copied().unwrap_or(0),
+                percentiles,
+                histogram,
+            },
+            death_causes,
+            mean_duration_secs: results
+                .iter()
+                .map(|r| r.duration.as_secs_f64())
+                .sum::<f64>()
+                / n,
+            branches,
+            physics_steps: results.iter().map(|r| r.physics_steps).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{difficulty::Difficulties, game::GameMode};
+
+    fn observe_all(branches: &[Branch]) -> BranchStats {
+        let mut stats = BranchStats::default();
+        let mut gap = 0;
+        for &b in branches {
+            stats.observe(b, &mut gap);
+        }
+        stats
+    }
+
+    fn simulation(mode: GameMode, max_chops: u32) -> Simulation {
+        Simulation {
+            profile: Difficulties::load_or_default().profile(mode),
+            max_chops,
+            physics: false,
+        }
+    }
+
+    fn perfect() -> PerfectPolicy {
+        PerfectPolicy {
+            reaction: Duration::from_millis(20),
+        }
+    }
+
+    #[test]
+    fn observe_counts_sides_and_gaps() {
+        use Branch::*;
+        let stats = observe_all(&[None, Left, None, None, Right, Right, None]);
+        assert_eq!(stats.segments, 7);
+        assert_eq!(stats.left, 1);
+        assert_eq!(stats.right, 2);
+        assert_eq!(stats.longest_gap, 2);
+    }
+
+    #[test]
+    fn merge_adds_counts_and_keeps_longest_gap() {
+        use Branch::*;
+        let mut stats = observe_all(&[None, None, None, Left]);
+        stats.merge(&observe_all(&[Right, None]));
+        assert_eq!(stats.segments, 6);
+        assert_eq!(stats.left, 1);
+        assert_eq!(stats.right, 1);
+        assert_eq!(stats.longest_gap, 3);
+    }
+
+    #[test]
+    fn same_seed_gives_same_report() {
+        let sim = simulation(GameMode::Classic, 500);
+        let report = |seed| {
+            let mut policy = HumanPolicy::new(0.25, 0.06, 0.02, seed);
+            let results: Vec<_> = (0..20)
+                .map(|i| sim.run_game(&mut policy, seed + i))
+                .collect();
+            serde_json::to_string(&Report::new(&results, 10)).unwrap()
+        };
+        assert_eq!(report(3), report(3));
+        assert_ne!(report(3), report(4));
+    }
+
+    #[test]
+    fn counts_every_grown_segment_once() {
+        // The classic tree keeps its height, so each chop grows one segment
+        let result = simulation(GameMode::Classic, 300).run_game(&mut perfect(), 1);
+        assert!(matches!(result.cause, DeathCause::ChopLimit));
+        assert_eq!(result.branches.segments, result.score as u64);
+    }
+
+    #[test]
+    fn gaps_respect_the_profile() {
+        let sim = simulation(GameMode::Expert, 300);
+        let max_empty_run = sim.profile.levels.iter().map(|l| l.max_empty_run).max();
+        for seed in 0..10 {
+            let result = sim.run_game(&mut perfect(), seed);
+            assert!(Some(result.branches.longest_gap) <= max_empty_run);
+        }
+    }
+}
@@ -1,75 +1,467 @@
-use image::{imageops, DynamicImage, GenericImage, Rgb, Rgba, RgbaImage};
-use rusttype::{point, Font, Scale};
+use image::{imageops, Rgba, RgbaImage};
+use rusttype::{point, PositionedGlyph, Rect, Scale};
+use std::collections::HashMap;
 
-const FONT_DATA: &[u8] = include_bytes!("../fonts/Courier New.ttf");
+use crate::{
+    fonts::{FontRole, Fonts, GlyphKey},
+    object::Color,
+};
 
-pub fn make_char_image(c: char, font: Font, scale: Scale, color: Rgb<u8>) -> RgbaImage {
-    let glyph = font.glyph(c).scaled(scale).positioned(point(0., 0.));
-    let bounding_box = glyph.pixel_bounding_box().unwrap();
+// Glyphs are rasterized at this size in pixels, enough for the largest text
+const GLYPH_SIZE: f32 = 128.;
+const ATLAS_SIZE: u32 = 2048;
+// Empty pixels around each glyph so that filtering does not pick up its neighbours
+const PADDING: u32 = 2;
 
-    let mut image =
-        DynamicImage::new_rgba8(bounding_box.width() as u32, bounding_box.height() as u32);
+// A glyph on a line, `x` being its pen position in line heights from the start of the line
+struct LineGlyph {
+    key: GlyphKey,
+    x: f32,
+}
 
-    let [r, g, b] = color.0;
+struct LineLayout {
+    glyphs: Vec<LineGlyph>,
+    // Advance of the whole line, in line heights
+    width: f32,
+}
 
-    glyph.draw(|x, y, v| image.put_pixel(x as u32, y as u32, Rgba([r, g, b, (v * 255.) as u8])));
+// Places the characters using the advance widths and kerning of the fonts.
+// Kerning only applies between glyphs of the same font.
+fn layout_line(fonts: &Fonts, role: FontRole, text: &str) -> LineLayout {
+    let scale = Scale::uniform(1.);
+    let mut glyphs = vec![];
+    let mut x = 0.;
+    let mut previous: Option<GlyphKey> = None;
+    for c in text.chars() {
+        let key = fonts.glyph(role, c);
+        let font = fonts.font(key.font);
+        if let Some(previous) = previous.filter(|p| p.font == key.font) {
+            x += font.pair_kerning(scale, previous.id, key.id);
+        }
+        glyphs.push(LineGlyph { key, x });
+        x += font.glyph(key.id).scaled(scale).h_metrics().advance_width;
+        previous = Some(key);
+    }
+    LineLayout { glyphs, width: x }
+}
 
-    imageops::flip_vertical(&image)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
 }
 
-pub fn make_char(c: char) -> RgbaImage {
-    let scale = rusttype::Scale::uniform(256.0);
-    let font = rusttype::Font::try_from_bytes(FONT_DATA as &[u8]).expect("Constructing font");
-    make_char_image(c, font, scale, Rgb([255, 255, 255]))
+// Drawn behind the text to keep it readable on any background
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Effect {
+    None,
+    Shadow { offset: (f32, f32), color: Color },
+    Outline { width: f32, color: Color },
 }
 
-pub fn make_text_image(text: &str, font: Font, scale: Scale, color: Rgb<u8>) -> RgbaImage {
-    let v_metrics = font.v_metrics(scale);
+// Sizes are in UI units
+#[derive(Debug, Clone, Copy)]
+pub struct TextStyle {
+    // Height of the capitals
+    pub size: f32,
+    pub align: Align,
+    // Distance between baselines relative to `size`
+    pub line_spacing: f32,
+    pub effect: Effect,
+    pub font: FontRole,
+}
+
+impl TextStyle {
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            align: Align::Center,
+            line_spacing: 1.6,
+            effect: Effect::None,
+            font: FontRole::Body,
+        }
+    }
+
+    pub fn align(self, align: Align) -> Self {
+        Self { align, ..self }
+    }
+
+    pub fn line_spacing(self, line_spacing: f32) -> Self {
+        Self {
+            line_spacing,
+            ..self
+        }
+    }
+
+    pub fn effect(self, effect: Effect) -> Self {
+        Self { effect, ..self }
+    }
+
+    pub fn font(self, font: FontRole) -> Self {
+        Self { font, ..self }
+    }
+}
+
+// Part of a text in one colour
+#[derive(Debug, Clone)]
+pub struct TextRun {
+    pub text: String,
+    pub color: Color,
+}
 
-    // layout the glyphs in a line with 20 pixels padding
-    let glyphs: Vec<_> = font
-        .layout(text, scale, point(20.0, 20.0 + v_metrics.ascent))
+impl TextRun {
+    pub fn new(text: &str, color: Color) -> Self {
+        Self {
+            text: text.to_string(),
+            color,
+        }
+    }
+}
+
+// A glyph placed by `layout`, its pen position in UI units from the anchor
+#[derive(Debug, Clone, Copy)]
+pub struct PlacedGlyph {
+    pub key: GlyphKey,
+    pub x: f32,
+    pub y: f32,
+    pub color: Color,
+}
+
+pub struct TextLayout {
+    pub glyphs: Vec<PlacedGlyph>,
+    // Size of a line height in UI units, glyphs are drawn at this scale
+    pub scale: f32,
+    // Area covered by the capitals of every line, relative to the anchor
+    pub min: (f32, f32),
+    pub max: (f32, f32),
+}
+
+fn line_text(chars: &[(char, Color)]) -> String {
+    chars.iter().map(|&(c, _)| c).collect()
+}
+
+// Splits the text at newlines, and at spaces where a line would get wider
+// than `max_width` in line heights. Words longer than that stay whole.
+fn wrap(
+    fonts: &Fonts,
+    role: FontRole,
+    chars: &[(char, Color)],
+    max_width: Option<f32>,
+) -> Vec<Vec<(char, Color)>> {
+    let mut lines = vec![];
+    for paragraph in chars.split(|&(c, _)| c == '\n') {
+        let max_width = match max_width {
+            Some(max_width) => max_width,
+            None => {
+                lines.push(paragraph.to_vec());
+                continue;
+            }
+        };
+        let mut line: Vec<(char, Color)> = vec![];
+        for word in paragraph.split(|&(c, _)| c == ' ') {
+            let mut candidate = line.clone();
+            if !line.is_empty() {
+                candidate.push((' ', line[line.len() - 1].1));
+            }
+            candidate.extend_from_slice(word);
+            if !line.is_empty()
+                && layout_line(fonts, role, &line_text(&candidate)).width > max_width
+            {
+                lines.push(line);
+                line = word.to_vec();
+            } else {
+                line = candidate;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// Lays out the runs as lines aligned on the anchor, wrapped to `max_width` UI
+// units if given. The block of lines is centered vertically on the anchor.
+pub fn layout(
+    fonts: &Fonts,
+    runs: &[TextRun],
+    style: &TextStyle,
+    max_width: Option<f32>,
+) -> TextLayout {
+    let scale = style.size / fonts.cap_height(style.font);
+    let chars: Vec<_> = runs
+        .iter()
+        .flat_map(|run| run.text.chars().map(move |c| (c, run.color)))
         .collect();
+    let lines = wrap(fonts, style.font, &chars, max_width.map(|w| w / scale));
 
-    // work out the layout size
-    let glyphs_height = (v_metrics.ascent - v_metrics.descent).ceil() as u32;
-    let glyphs_width = {
-        let min_x = glyphs
-            .first()
-            .map(|g| g.pixel_bounding_box().unwrap().min.x)
-            .unwrap();
-        let max_x = glyphs
-            .last()
-            .map(|g| g.pixel_bounding_box().unwrap().max.x)
-            .unwrap();
-        (max_x - min_x) as u32
+    let spacing = style.size * style.line_spacing;
+    let bottom = -spacing * (lines.len() - 1) as f32;
+    // Moves the block so that it is centered on the anchor
+    let shift = -(style.size + bottom) / 2.;
+    let mut glyphs = vec![];
+    let mut width: f32 = 0.;
+    for (i, line) in lines.iter().enumerate() {
+        let layout = layout_line(fonts, style.font, &line_text(line));
+        let line_width = layout.width * scale;
+        width = width.max(line_width);
+        let start = match style.align {
+            Align::Left => 0.,
+            Align::Center => -line_width / 2.,
+            Align::Right => -line_width,
+        };
+        let y = shift - spacing * i as f32;
+        glyphs.extend(
+            layout
+                .glyphs
+                .iter()
+                .zip(line)
+                .map(|(glyph, &(_, color))| PlacedGlyph {
+                    key: glyph.key,
+                    x: start + glyph.x * scale,
+                    y,
+                    color,
+                }),
+        );
+    }
+    let left = match style.align {
+        Align::Left => 0.,
+        Align::Center => -width / 2.,
+        Align::Right => -width,
     };
+    TextLayout {
+        glyphs,
+        scale,
+        min: (left, shift + bottom),
+        max: (left + width, shift + style.size),
+    }
+}
+
+// Where a glyph is drawn, in line heights from its pen position with y going up,
+// and the matching corners in the atlas texture
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasGlyph {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+}
+
+// Glyphs rasterized into a single image as they are needed, packed in rows
+pub struct GlyphAtlas {
+    image: RgbaImage,
+    // None for glyphs with nothing to draw, such as spaces
+    glyphs: HashMap<GlyphKey, Option<AtlasGlyph>>,
+    next: (u32, u32),
+    row_height: u32,
+    // Pixels drawn since the last `take_changed`
+    changed: Option<Rect<u32>>,
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    // Create a new rgb image with some padding
-    let mut image = DynamicImage::new_rgba8(glyphs_width + 40, glyphs_height + 40);
-
-    let [r, g, b] = color.0;
-
-    // Loop through the glyphs in the text, positing each one on a line
-    for glyph in glyphs {
-        if let Some(bounding_box) = glyph.pixel_bounding_box() {
-            // Draw the glyph into the image per-pixel by using the draw closure
-            glyph.draw(|x, y, v| {
-                image.put_pixel(
-                    // Offset the position by the glyph bounding box
-                    x + bounding_box.min.x as u32 - 20,
-                    y + bounding_box.min.y as u32,
-                    // Turn the coverage into an alpha value
-                    Rgba([r, g, b, (v * 255.) as u8]),
-                )
-            });
+impl GlyphAtlas {
+    pub fn new() -> Self {
+        Self {
+            image: RgbaImage::new(ATLAS_SIZE, ATLAS_SIZE),
+            glyphs: HashMap::new(),
+            next: (0, 0),
+            row_height: 0,
+            changed: None,
         }
     }
-    imageops::flip_vertical(&image)
+
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn contains(&self, key: GlyphKey) -> bool {
+        self.glyphs.contains_key(&key)
+    }
+
+    // None for glyphs that are not in the atlas or have nothing to draw
+    pub fn get(&self, key: GlyphKey) -> Option<AtlasGlyph> {
+        self.glyphs.get(&key).copied().flatten()
+    }
+
+    // The region drawn since the last call, as its offset in the image and
+    // its pixels
+    pub fn take_changed(&mut self) -> Option<([u32; 2], RgbaImage)> {
+        let rect = self.changed.take()?;
+        let pixels = imageops::crop_imm(
+            &self.image,
+            rect.min.x,
+            rect.min.y,
+            rect.width(),
+            rect.height(),
+        );
+        Some(([rect.min.x, rect.min.y], pixels.to_image()))
+    }
+
+    // Rasterizes the glyph if it is not in the atlas yet. Once the atlas is
+    // full it starts over empty, glyphs that are still used are added back
+    // as they go missing.
+    pub fn insert(&mut self, fonts: &Fonts, key: GlyphKey) -> Option<AtlasGlyph> {
+        if let Some(&glyph) = self.glyphs.get(&key) {
+            return glyph;
+        }
+        let glyph = fonts
+            .font(key.font)
+            .glyph(key.id)
+            .scaled(Scale::uniform(GLYPH_SIZE))
+            .positioned(point(0., 0.));
+        let bounds = match glyph.pixel_bounding_box() {
+            Some(bounds) => bounds,
+            None => {
+                self.glyphs.insert(key, None);
+                return None;
+            }
+        };
+        let entry = match self.draw(&glyph, bounds) {
+            Some(entry) => entry,
+            None => {
+                eprintln!("glyph atlas is full, clearing it");
+                self.clear();
+                self.draw(&glyph, bounds)?
+            }
+        };
+        self.glyphs.insert(key, Some(entry));
+        Some(entry)
+    }
+
+    fn clear(&mut self) {
+        for pixel in self.image.pixels_mut() {
+            *pixel = Rgba([0; 4]);
+        }
+        self.glyphs.clear();
+        self.next = (0, 0);
+        self.row_height = 0;
+        self.changed = Some(Rect {
+            min: point(0, 0),
+            max: point(ATLAS_SIZE, ATLAS_SIZE),
+        });
+    }
+
+    fn mark_changed(&mut self, rect: Rect<u32>) {
+        self.changed = Some(match self.changed {
+            Some(changed) => Rect {
+                min: point(changed.min.x.min(rect.min.x), changed.min.y.min(rect.min.y)),
+                max: point(changed.max.x.max(rect.max.x), changed.max.y.max(rect.max.y)),
+            },
+            None => rect,
+        });
+    }
+
+    fn draw(&mut self, glyph: &PositionedGlyph, bounds: Rect<i32>) -> Option<AtlasGlyph> {
+        let width = bounds.width() as u32 + 2 * PADDING;
+        let height = bounds.height() as u32 + 2 * PADDING;
+        if self.next.0 + width > ATLAS_SIZE {
+            self.next = (0, self.next.1 + self.row_height);
+            self.row_height = 0;
+        }
+        if self.next.1 + height > ATLAS_SIZE {
+            return None;
+        }
+        let (left, top) = (self.next.0 + PADDING, self.next.1 + PADDING);
+        let image = &mut self.image;
+        glyph.draw(|x, y, v| {
+            image.put_pixel(left + x, top + y, Rgba([255, 255, 255, (v * 255.) as u8]))
+        });
+        self.mark_changed(Rect {
+            min: point(self.next.0, self.next.1),
+            max: point(self.next.0 + width, self.next.1 + height),
+        });
+        self.next.0 += width;
+        self.row_height = self.row_height.max(height);
+
+        // The first row of the image is at the bottom of the texture
+        let size = ATLAS_SIZE as f32;
+        let right = left + bounds.width() as u32;
+        let bottom = top + bounds.height() as u32;
+        Some(AtlasGlyph {
+            min: [
+                bounds.min.x as f32 / GLYPH_SIZE,
+                -bounds.max.y as f32 / GLYPH_SIZE,
+            ],
+            max: [
+                bounds.max.x as f32 / GLYPH_SIZE,
+                -bounds.min.y as f32 / GLYPH_SIZE,
+            ],
+            uv_min: [left as f32 / size, bottom as f32 / size],
+            uv_max: [right as f32 / size, top as f32 / size],
+        })
+    }
 }
 
-pub fn make_text(text: &str) -> RgbaImage {
-    let scale = rusttype::Scale::uniform(256.0);
-    let font = rusttype::Font::try_from_bytes(FONT_DATA as &[u8]).expect("Constructing font");
-    make_text_image(text, font, scale, Rgb([255, 255, 255]))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        assets::{AssetManifest, MANIFEST_PATH},
+        object::WHITE,
+    };
+    use std::path::Path;
+
+    const RED: Color = [1., 0., 0., 1.];
+
+    fn fonts() -> Fonts {
+        let manifest = AssetManifest::load(Path::new(MANIFEST_PATH)).unwrap();
+        Fonts::load(&manifest.fonts).unwrap()
+    }
+
+    fn wrapped(fonts: &Fonts, text: &str, max_width: Option<f32>) -> Vec<String> {
+        let chars: Vec<_> = text.chars().map(|c| (c, WHITE)).collect();
+        wrap(fonts, FontRole::Body, &chars, max_width)
+            .iter()
+            .map(|line| line_text(line))
+            .collect()
+    }
+
+    fn width(fonts: &Fonts, text: &str) -> f32 {
+        layout_line(fonts, FontRole::Body, text).width
+    }
+
+    #[test]
+    fn splits_only_at_newlines_without_width() {
+        let fonts = fonts();
+        assert_eq!(
+            wrapped(&fonts, "one two\nthree", None),
+            vec!["one two", "three"]
+        );
+    }
+
+    #[test]
+    fn wraps_at_spaces() {
+        let fonts = fonts();
+        let max_width = width(&fonts, "one two");
+        assert_eq!(
+            wrapped(&fonts, "one two three", Some(max_width)),
+            vec!["one two", "three"]
+        );
+        assert_eq!(
+            wrapped(&fonts, "one two\nthree four", Some(max_width)),
+            vec!["one two", "three", "four"]
+        );
+    }
+
+    #[test]
+    fn long_words_stay_whole() {
+        let fonts = fonts();
+        let max_width = width(&fonts, "ab");
+        assert_eq!(
+            wrapped(&fonts, "abcdef gh", Some(max_width)),
+            vec!["abcdef", "gh"]
+        );
+    }
+
+    #[test]
+    fn keeps_colors() {
+        let fonts = fonts();
+        let chars = [('a', RED), (' ', RED), ('b', WHITE)];
+        let lines = wrap(&fonts, FontRole::Body, &chars, Some(width(&fonts, "a")));
+        assert_eq!(lines, vec![vec![('a', RED)], vec![('b', WHITE)]]);
+    }
 }
@@ -1,4 +1,5 @@
 in vec2 v_uv;
+in vec4 v_color;
 
 out vec4 frag_color;
 
@@ -6,5 +7,5 @@ uniform sampler2D tex;
 
 void main() {
     vec4 texColor = texture(tex, v_uv);
-    frag_color = texColor;
+    frag_color = texColor * v_color;
 }
\ No newline at end of file
@@ -1,11 +1,12 @@
-in vec2 uv;
 in vec3 position;
+in vec2 uv;
+in vec4 color;
 
 out vec2 v_uv;
-
-uniform mat4 model;
+out vec4 v_color;
 
 void main() {
-     gl_Position = model * vec4(position, 1.);
+     gl_Position = vec4(position, 1.);
      v_uv = uv;
+     v_color = color;
 }
\ No newline at end of file